@@ -7,12 +7,15 @@ use clap_blocks::{
     tokio::TokioDatafusionConfig,
 };
 use datafusion_util::config::register_iox_object_store;
+use hyper::header::HeaderName;
 use influxdb3_process::{
     build_malloc_conf, setup_metric_registry, INFLUXDB3_GIT_HASH, INFLUXDB3_VERSION, PROCESS_UUID,
 };
 use influxdb3_server::{
-    auth::AllOrNothingAuthorizer, builder::ServerBuilder, query_executor::QueryExecutorImpl, serve,
-    CommonServerState,
+    auth::{AllOrNothingAuthorizer, AuthRealm, DisableAuthz, DEFAULT_AUTH_REALM},
+    builder::ServerBuilder,
+    query_executor::QueryExecutorImpl,
+    serve, CommonServerState,
 };
 use influxdb3_write::persister::PersisterImpl;
 use influxdb3_write::wal::WalImpl;
@@ -27,7 +30,7 @@ use panic_logging::SendPanicsToTracing;
 use parquet_file::storage::{ParquetStorage, StorageId};
 use std::collections::HashMap;
 use std::{
-    num::NonZeroUsize,
+    num::{NonZeroU32, NonZeroUsize},
     path::{Path, PathBuf},
     sync::Arc,
 };
@@ -147,6 +150,65 @@ pub struct Config {
     #[clap(long = "bearer-token", env = "INFLUXDB3_BEARER_TOKEN", action)]
     pub bearer_token: Option<String>,
 
+    /// The realm sent in the `WWW-Authenticate` header of unauthorized responses. The realm
+    /// cannot contain control characters.
+    #[clap(
+        long = "auth-realm",
+        env = "INFLUXDB3_AUTH_REALM",
+        default_value = DEFAULT_AUTH_REALM,
+        action
+    )]
+    pub auth_realm: AuthRealm,
+
+    /// An alternative header to read credentials from, in the same `<scheme> <token>` form
+    /// as the `Authorization` header, for use behind proxies that consume that header. The
+    /// `Authorization` header is still accepted, and takes precedence.
+    #[clap(long = "auth-header-name", env = "INFLUXDB3_AUTH_HEADER_NAME", action)]
+    pub auth_header_name: Option<HeaderName>,
+
+    /// Serve requests without authorization for the given comma-separated rules. A rule is
+    /// either a resource, one of `health`, `ping`, or `metrics`, or a single route given as
+    /// `<METHOD>:<path>`, e.g., `GET:/api/v3/query_sql`.
+    #[clap(
+        long = "disable-authz",
+        env = "INFLUXDB3_DISABLE_AUTHZ",
+        value_delimiter = ',',
+        action
+    )]
+    pub disable_authz: Vec<DisableAuthz>,
+
+    /// The database used by write and query requests that do not provide a `db` parameter
+    #[clap(long = "default-db", env = "INFLUXDB3_DEFAULT_DB", action)]
+    pub default_db: Option<String>,
+
+    /// The maximum number of requests per second that can be made with each token. Requests
+    /// over the limit are rejected with a 429. Only tokens validated against `--bearer-token`
+    /// are limited. If not specified, requests are not rate limited.
+    #[clap(
+        long = "rate-limit-per-token",
+        env = "INFLUXDB3_RATE_LIMIT_PER_TOKEN",
+        action
+    )]
+    pub rate_limit_per_token: Option<NonZeroU32>,
+
+    /// Report the server as unhealthy, by responding to `/health` with a 503, while the write
+    /// buffer is filled past this percentage of `--buffer-mem-limit-mb`. This allows load
+    /// balancers to route writes away from the server until it has persisted its buffer.
+    /// If not specified, the health endpoint does not consider the write buffer.
+    #[clap(
+        long = "health-buffer-threshold-percent",
+        env = "INFLUXDB3_HEALTH_BUFFER_THRESHOLD_PERCENT",
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        action
+    )]
+    pub health_buffer_threshold_percent: Option<u8>,
+
+    /// The time, e.g., `30s`, after which a query to `/api/v3/query_sql` is cancelled and fails
+    /// with a 408. Queries can override this with the `timeout` parameter. If not specified,
+    /// queries do not time out unless they provide their own timeout.
+    #[clap(long = "query-timeout", env = "INFLUXDB3_QUERY_TIMEOUT", action)]
+    pub query_timeout: Option<humantime::Duration>,
+
     /// Duration of wal segments that are persisted to object storage. Valid values: 1m, 5m, 10m,
     /// 15m, 30m, 1h, 2h, 4h.
     #[clap(
@@ -168,6 +230,26 @@ pub struct Config {
     )]
     pub query_log_size: usize,
 
+    /// The maximum number of chunks, i.e., buffered segments and persisted files, that a
+    /// query may scan from a single table. Queries exceeding the limit fail with an error.
+    /// If not specified, queries are not limited.
+    #[clap(
+        long = "max-chunks-per-query",
+        env = "INFLUXDB3_MAX_CHUNKS_PER_QUERY",
+        action
+    )]
+    pub max_chunks_per_query: Option<NonZeroUsize>,
+
+    /// Return the columns of tables queried with `SELECT *` in a canonical order: tags, then
+    /// fields, each sorted by name, then `time`. By default, columns are sorted by name.
+    #[clap(
+        long = "canonical-column-order",
+        env = "INFLUXDB3_CANONICAL_COLUMN_ORDER",
+        default_value_t = false,
+        action
+    )]
+    pub canonical_column_order: bool,
+
     // TODO - make this default to 70% of available memory:
     /// The size limit of the open segments in the write buffer.
     #[clap(
@@ -298,10 +380,19 @@ pub async fn command(config: Config) -> Result<()> {
         Arc::new(config.datafusion_config),
         10,
         config.query_log_size,
+        config.max_chunks_per_query,
+        config.canonical_column_order,
     ));
 
     let builder = ServerBuilder::new(common_state)
         .max_request_size(config.max_http_request_size)
+        .auth_realm(config.auth_realm)
+        .auth_header_name(config.auth_header_name)
+        .disable_authz(config.disable_authz)
+        .default_db(config.default_db)
+        .rate_limit_per_token(config.rate_limit_per_token)
+        .health_buffer_threshold_percent(config.health_buffer_threshold_percent)
+        .query_timeout(config.query_timeout.map(Into::into))
         .write_buffer(write_buffer)
         .query_executor(query_executor)
         .time_provider(time_provider)
@@ -1,5 +1,6 @@
 use arrow_flight::error::FlightError;
 use arrow_util::assert_batches_sorted_eq;
+use base64::{engine::general_purpose::STANDARD, Engine};
 use influxdb3_client::Precision;
 use reqwest::StatusCode;
 
@@ -114,6 +115,316 @@ async fn auth() {
     );
 }
 
+#[tokio::test]
+async fn auth_api_key_scheme() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .spawn()
+        .await;
+
+    let client = reqwest::Client::new();
+    let write_lp_url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+    let write_lp_params = [("db", "foo")];
+
+    assert_eq!(
+        client
+            .post(&write_lp_url)
+            .query(&write_lp_params)
+            .header("Authorization", format!("ApiKey {TOKEN}"))
+            .body("cpu,host=a val=1i 123")
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+    // The auth scheme is case sensitive:
+    assert_eq!(
+        client
+            .post(&write_lp_url)
+            .query(&write_lp_params)
+            .header("Authorization", format!("apikey {TOKEN}"))
+            .body("cpu,host=a val=1i 123")
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::BAD_REQUEST
+    );
+}
+
+#[tokio::test]
+async fn auth_www_authenticate_header() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .spawn()
+        .await;
+
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", "select * from cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        resp.headers()
+            .get("WWW-Authenticate")
+            .expect("WWW-Authenticate header is present")
+            .to_str()
+            .unwrap(),
+        r#"Bearer realm="influxdb3""#
+    );
+}
+
+#[tokio::test]
+async fn auth_disabled_for_get_query_only() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .with_disable_authz(["GET:/api/v3/query_sql"])
+        .spawn()
+        .await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.9", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let base = server.client_addr();
+    let query_sql_url = format!("{base}/api/v3/query_sql");
+    let query_sql_params = [("db", "foo"), ("q", "select * from cpu")];
+
+    // An unauthenticated GET query is allowed:
+    assert_eq!(
+        client
+            .get(&query_sql_url)
+            .query(&query_sql_params)
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+    // An unauthenticated POST query still requires auth:
+    assert_eq!(
+        client
+            .post(&query_sql_url)
+            .json(&serde_json::json!({"db": "foo", "q": "select * from cpu"}))
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+    // An unauthenticated write still requires auth:
+    assert_eq!(
+        client
+            .post(format!("{base}/api/v3/write_lp"))
+            .query(&[("db", "foo")])
+            .body("cpu,host=a usage=0.5")
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn auth_per_token_request_metrics() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .spawn()
+        .await;
+
+    for i in 0..3 {
+        server
+            .write_lp_to_db("foo", format!("cpu,host=a usage={i}"), Precision::Second)
+            .await
+            .unwrap();
+    }
+
+    let metrics = reqwest::Client::new()
+        .get(format!("{base}/metrics", base = server.client_addr()))
+        .bearer_auth(TOKEN)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+
+    let request_counts = metrics
+        .lines()
+        .filter(|line| line.starts_with("influxdb3_http_requests_by_token"))
+        .collect::<Vec<_>>();
+    // Only the configured token was used, so there is a single series for it:
+    assert_eq!(request_counts.len(), 1, "got: {request_counts:?}");
+    assert!(request_counts[0].contains("token_id=\""));
+    let request_count = request_counts[0]
+        .split_whitespace()
+        .last()
+        .and_then(|count| count.parse::<u64>().ok())
+        .expect("metrics include a request count for the token");
+    assert!(request_count >= 3, "got request count: {request_count}");
+    assert!(
+        !metrics.contains(TOKEN),
+        "metrics must not expose the token"
+    );
+    // Nor the hash of it that the server is configured with:
+    assert!(!metrics.contains(&HASHED_TOKEN[..16]));
+}
+
+#[tokio::test]
+async fn auth_rate_limit_per_token() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .with_rate_limit_per_token(2)
+        .spawn()
+        .await;
+
+    let client = reqwest::Client::new();
+    let write_lp_url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+
+    let mut statuses = vec![];
+    for _ in 0..10 {
+        statuses.push(
+            client
+                .post(&write_lp_url)
+                .query(&[("db", "foo")])
+                .bearer_auth(TOKEN)
+                .body("cpu,host=a usage=0.5")
+                .send()
+                .await
+                .unwrap(),
+        );
+    }
+    let limited = statuses
+        .iter()
+        .find(|resp| resp.status() == StatusCode::TOO_MANY_REQUESTS)
+        .expect("some requests were rate limited");
+    assert!(limited.headers().contains_key("Retry-After"));
+
+    // Tokens that are not valid are rejected, and are not given a rate limit of their own:
+    assert_eq!(
+        client
+            .post(&write_lp_url)
+            .query(&[("db", "foo")])
+            .bearer_auth("token-b")
+            .body("cpu,host=a usage=0.5")
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn auth_rate_limit_requires_bearer_token() {
+    // Without an auth token configured, any bearer token is accepted, so tokens are
+    // not rate limited, as a client could evade the limit by varying its token:
+    let server = TestServer::configure()
+        .with_rate_limit_per_token(2)
+        .spawn()
+        .await;
+
+    let client = reqwest::Client::new();
+    let write_lp_url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+    for _ in 0..10 {
+        assert_eq!(
+            client
+                .post(&write_lp_url)
+                .query(&[("db", "foo")])
+                .bearer_auth("token-a")
+                .body("cpu,host=a usage=0.5")
+                .send()
+                .await
+                .unwrap()
+                .status(),
+            StatusCode::OK
+        );
+    }
+}
+
+#[tokio::test]
+async fn auth_alternative_header_name() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .with_auth_header_name("X-Influx-Token")
+        .spawn()
+        .await;
+
+    let client = reqwest::Client::new();
+    let base = server.client_addr();
+    let write_lp_url = format!("{base}/api/v3/write_lp");
+    let query_sql_url = format!("{base}/api/v3/query_sql");
+    let query_sql_params = [("db", "foo"), ("q", "select * from cpu")];
+
+    assert_eq!(
+        client
+            .post(&write_lp_url)
+            .query(&[("db", "foo")])
+            .body("cpu,host=a val=1i 123")
+            .header("X-Influx-Token", format!("Bearer {TOKEN}"))
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        client
+            .get(&query_sql_url)
+            .query(&query_sql_params)
+            .header("X-Influx-Token", format!("Bearer {TOKEN}"))
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+    // The standard header still works:
+    assert_eq!(
+        client
+            .get(&query_sql_url)
+            .query(&query_sql_params)
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        client
+            .get(&query_sql_url)
+            .query(&query_sql_params)
+            .header("X-Influx-Token", "Bearer wrong")
+            .send()
+            .await
+            .unwrap()
+            .status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
 #[tokio::test]
 async fn auth_grpc() {
     const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
@@ -171,33 +482,46 @@ async fn auth_grpc() {
     {
         let mut client = server.flight_sql_client("foo").await;
         let error = client.query("SELECT * FROM cpu").await.unwrap_err();
-        assert!(matches!(error, FlightError::Tonic(s) if s.code() == tonic::Code::Unauthenticated));
+        let FlightError::Tonic(status) = error else {
+            panic!("expected a tonic error, got: {error}");
+        };
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        assert_eq!(auth_error_detail(&status)["error"], "missing_header");
     }
 
     // Create some new clients that set the authorization header incorrectly to
     // ensure errors are returned:
 
     // Misspelled "Bearer"
-    {
+    let misspelled_scheme_detail = {
         let mut client = server.flight_sql_client("foo").await;
         client
             .add_header("authorization", &format!("bearer {TOKEN}"))
             .unwrap();
         let error = client.query("SELECT * FROM cpu").await.unwrap_err();
-        assert!(matches!(error, FlightError::Tonic(s) if s.code() == tonic::Code::Unauthenticated));
-    }
+        let FlightError::Tonic(status) = error else {
+            panic!("expected a tonic error, got: {error}");
+        };
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+        auth_error_detail(&status)
+    };
+    assert_eq!(misspelled_scheme_detail["error"], "unknown_scheme");
 
     // Invalid token, this actually gives Permission denied
-    {
+    let invalid_token_detail = {
         let mut client = server.flight_sql_client("foo").await;
         client
             .add_header("authorization", "Bearer invalid-token")
             .unwrap();
         let error = client.query("SELECT * FROM cpu").await.unwrap_err();
-        assert!(
-            matches!(error, FlightError::Tonic(s) if s.code() == tonic::Code::PermissionDenied)
-        );
-    }
+        let FlightError::Tonic(status) = error else {
+            panic!("expected a tonic error, got: {error}");
+        };
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+        auth_error_detail(&status)
+    };
+    assert_eq!(invalid_token_detail["error"], "invalid_token");
+    assert_ne!(misspelled_scheme_detail, invalid_token_detail);
 
     // Misspelled header key
     {
@@ -210,6 +534,139 @@ async fn auth_grpc() {
     }
 }
 
+#[tokio::test]
+async fn auth_grpc_basic() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .spawn()
+        .await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    // The password given with the Basic scheme is used as the token, and the
+    // username is ignored:
+    let mut client = server.flight_sql_client("foo").await;
+    client
+        .add_header(
+            "authorization",
+            &format!("Basic {}", STANDARD.encode(format!("user:{TOKEN}"))),
+        )
+        .unwrap();
+    let response = client
+        .query("SELECT host, region, time, usage FROM cpu")
+        .await
+        .unwrap();
+    let batches = collect_stream(response).await;
+    assert_batches_sorted_eq!(
+        [
+            "+------+---------+--------------------------------+-------+",
+            "| host | region  | time                           | usage |",
+            "+------+---------+--------------------------------+-------+",
+            "| s1   | us-east | 1970-01-01T00:00:00.000000001Z | 0.9   |",
+            "+------+---------+--------------------------------+-------+",
+        ],
+        &batches
+    );
+
+    // A Basic handshake succeeds with the correct password:
+    let mut client = server.flight_client().await;
+    client
+        .add_header(
+            "authorization",
+            &format!("Basic {}", STANDARD.encode(format!("user:{TOKEN}"))),
+        )
+        .unwrap();
+    client.handshake(vec![]).await.unwrap();
+
+    // ...and fails with an incorrect one:
+    let mut client = server.flight_sql_client("foo").await;
+    client
+        .add_header(
+            "authorization",
+            &format!("Basic {}", STANDARD.encode("user:wrong")),
+        )
+        .unwrap();
+    let error = client.query("SELECT * FROM cpu").await.unwrap_err();
+    let FlightError::Tonic(status) = error else {
+        panic!("expected a tonic error, got: {error}");
+    };
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert_eq!(auth_error_detail(&status)["error"], "invalid_token");
+}
+
+#[tokio::test]
+async fn auth_grpc_api_key_and_alternative_header() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .with_auth_header_name("X-Influx-Token")
+        .spawn()
+        .await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    // Credentials are accepted with the ApiKey scheme, and in the alternative header, as
+    // they are on the HTTP API:
+    for (header, value) in [
+        ("authorization", format!("ApiKey {TOKEN}")),
+        ("x-influx-token", format!("Bearer {TOKEN}")),
+        ("x-influx-token", format!("ApiKey {TOKEN}")),
+    ] {
+        let mut client = server.flight_sql_client("foo").await;
+        client.add_header(header, &value).unwrap();
+        let response = client
+            .query("SELECT host, region, time, usage FROM cpu")
+            .await
+            .unwrap();
+        let batches = collect_stream(response).await;
+        assert_batches_sorted_eq!(
+            [
+                "+------+---------+--------------------------------+-------+",
+                "| host | region  | time                           | usage |",
+                "+------+---------+--------------------------------+-------+",
+                "| s1   | us-east | 1970-01-01T00:00:00.000000001Z | 0.9   |",
+                "+------+---------+--------------------------------+-------+",
+            ],
+            &batches
+        );
+    }
+
+    let mut client = server.flight_sql_client("foo").await;
+    client
+        .add_header("x-influx-token", "ApiKey invalid-token")
+        .unwrap();
+    let error = client.query("SELECT * FROM cpu").await.unwrap_err();
+    let FlightError::Tonic(status) = error else {
+        panic!("expected a tonic error, got: {error}");
+    };
+    assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    assert_eq!(auth_error_detail(&status)["error"], "invalid_token");
+}
+
+/// Parse the JSON error detail attached to a gRPC auth error
+fn auth_error_detail(status: &tonic::Status) -> serde_json::Value {
+    serde_json::from_slice(status.details()).expect("auth error detail is valid JSON")
+}
+
 #[tokio::test]
 async fn v1_password_parameter() {
     const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
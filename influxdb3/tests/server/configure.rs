@@ -0,0 +1,88 @@
+use hyper::StatusCode;
+use influxdb3_client::Precision;
+use pretty_assertions::assert_eq;
+use serde_json::{json, Value};
+
+use crate::TestServer;
+
+#[tokio::test]
+async fn api_v3_configure_table_schema() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9,count=3i,ok=true 1",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/configure/table", base = server.client_addr());
+
+    let resp = client
+        .get(&url)
+        .query(&[("db", "foo"), ("table", "cpu")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({
+            "db": "foo",
+            "table": "cpu",
+            "columns": [
+                { "name": "count", "kind": "field", "type": "integer" },
+                { "name": "host", "kind": "tag", "type": "string" },
+                { "name": "ok", "kind": "field", "type": "boolean" },
+                { "name": "region", "kind": "tag", "type": "string" },
+                { "name": "time", "kind": "time", "type": "timestamp" },
+                { "name": "usage", "kind": "field", "type": "float" },
+            ]
+        })
+    );
+
+    // Unknown databases and tables are not found:
+    for (db, table) in [("bar", "cpu"), ("foo", "mem")] {
+        let resp = client
+            .get(&url)
+            .query(&[("db", db), ("table", table)])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            resp.status(),
+            StatusCode::NOT_FOUND,
+            "db: {db}, table: {table}"
+        );
+    }
+
+    // The table must be given:
+    let resp = client
+        .get(&url)
+        .query(&[("db", "foo")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({ "error": "missing required parameter: table", "data": null })
+    );
+
+    // Malformed parameters are rejected:
+    let resp = client
+        .get(&url)
+        .query(&[("db", "foo"), ("table", "cpu"), ("table", "mem")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({
+            "error": "invalid table schema parameters: duplicate field `table`",
+            "data": null
+        })
+    );
+}
@@ -2,6 +2,7 @@ use crate::TestServer;
 use hyper::StatusCode;
 use influxdb3_client::Error;
 use influxdb3_client::Precision;
+use serde_json::Value;
 
 #[tokio::test]
 async fn limits() -> Result<(), Error> {
@@ -80,3 +81,136 @@ async fn limits() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn query_chunk_limit() {
+    let server = TestServer::configure()
+        .with_max_chunks_per_query(2)
+        .spawn()
+        .await;
+
+    // Write cpu data spanning three hours, so that it is buffered in three segments:
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1 usage=0.9 0\n\
+            cpu,host=s1 usage=0.8 3600\n\
+            cpu,host=s1 usage=0.7 7200",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", "SELECT * FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = resp.json::<Value>().await.unwrap();
+    assert_eq!(
+        body["error"],
+        "query would scan 3 chunks of table cpu, which exceeds the limit of 2 \
+        chunks per query"
+    );
+
+    // Queries over narrower time ranges only scan the segments that they overlap, and rows
+    // on the boundaries of those ranges are still returned:
+    let query = |predicate: &'static str| {
+        let server = &server;
+        async move {
+            let resp = server
+                .api_v3_query_sql(&[
+                    ("db", "foo"),
+                    (
+                        "q",
+                        &format!("SELECT host, usage FROM cpu WHERE {predicate} ORDER BY time"),
+                    ),
+                    ("format", "pretty"),
+                ])
+                .await;
+            assert_eq!(resp.status(), StatusCode::OK, "query failed: {predicate}");
+            resp.text().await.unwrap()
+        }
+    };
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | s1   | 0.7   |\n\
+        +------+-------+",
+        query("time >= '1970-01-01T01:30:00Z'").await
+    );
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | s1   | 0.7   |\n\
+        +------+-------+",
+        query("time >= '1970-01-01T02:00:00Z'").await
+    );
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | s1   | 0.9   |\n\
+        | s1   | 0.8   |\n\
+        +------+-------+",
+        query("time <= '1970-01-01T01:00:00Z'").await
+    );
+    assert_eq!(
+        "+------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | s1   | 0.8   |\n\
+        +------+-------+",
+        query("time = '1970-01-01T01:00:00Z'").await
+    );
+}
+
+#[tokio::test]
+async fn health_reports_write_buffer_backpressure() {
+    let server = TestServer::configure()
+        .with_buffer_mem_limit_mb(1)
+        .with_health_buffer_threshold_percent(50)
+        .spawn()
+        .await;
+    let client = reqwest::Client::new();
+    let health_url = format!("{base}/health", base = server.client_addr());
+
+    let resp = client.get(&health_url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Fill the write buffer well past the threshold:
+    let lp = (0..50_000).fold(String::new(), |mut acc, i| {
+        acc.push_str(&format!("cpu,host=s{} usage=0.9 {i}\n", i % 100));
+        acc
+    });
+    server
+        .write_lp_to_db("foo", lp, Precision::Nanosecond)
+        .await
+        .unwrap();
+
+    let resp = client.get(&health_url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body = resp.text().await.unwrap();
+    assert!(body.starts_with("BACKPRESSURE"), "unexpected body: {body}");
+
+    // Queries are still served while the server reports backpressure:
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", "SELECT count(*) FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    // Once the buffer has been persisted to free memory, the server reports healthy again:
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(60);
+    loop {
+        let resp = client.get(&health_url).send().await.unwrap();
+        if resp.status() == StatusCode::OK {
+            break;
+        }
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "server did not recover from backpressure"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+}
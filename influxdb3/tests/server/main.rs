@@ -14,6 +14,7 @@ use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Response;
 
 mod auth;
+mod configure;
 mod flight;
 mod limits;
 mod ping;
@@ -25,6 +26,15 @@ mod write;
 #[derive(Debug, Default)]
 pub struct TestConfig {
     auth_token: Option<(String, String)>,
+    disable_authz: Option<String>,
+    default_db: Option<String>,
+    rate_limit_per_token: Option<String>,
+    auth_header_name: Option<String>,
+    max_chunks_per_query: Option<String>,
+    canonical_column_order: bool,
+    buffer_mem_limit_mb: Option<String>,
+    health_buffer_threshold_percent: Option<String>,
+    max_http_request_size: Option<String>,
 }
 
 impl TestConfig {
@@ -38,6 +48,71 @@ impl TestConfig {
         self
     }
 
+    /// Disable authorization for the given resources or `<METHOD>:<path>` routes
+    pub fn with_disable_authz<I, S>(mut self, rules: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.disable_authz = Some(
+            rules
+                .into_iter()
+                .map(|r| r.as_ref().to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        self
+    }
+
+    /// Set the database used by requests that do not provide a `db` parameter
+    pub fn with_default_db<S: Into<String>>(mut self, db: S) -> Self {
+        self.default_db = Some(db.into());
+        self
+    }
+
+    /// Limit the number of requests per second that can be made with each token
+    pub fn with_rate_limit_per_token(mut self, limit: u32) -> Self {
+        self.rate_limit_per_token = Some(limit.to_string());
+        self
+    }
+
+    /// Set an alternative header that the server reads credentials from
+    pub fn with_auth_header_name(mut self, name: impl Into<String>) -> Self {
+        self.auth_header_name = Some(name.into());
+        self
+    }
+
+    /// Limit the number of chunks that a query may scan from a single table
+    pub fn with_max_chunks_per_query(mut self, limit: usize) -> Self {
+        self.max_chunks_per_query = Some(limit.to_string());
+        self
+    }
+
+    /// Return the columns of `SELECT *` queries as tags, then fields, then `time`
+    pub fn with_canonical_column_order(mut self) -> Self {
+        self.canonical_column_order = true;
+        self
+    }
+
+    /// Set the size, in MB, that the write buffer is limited to
+    pub fn with_buffer_mem_limit_mb(mut self, limit: usize) -> Self {
+        self.buffer_mem_limit_mb = Some(limit.to_string());
+        self
+    }
+
+    /// Report the server as unhealthy while the write buffer is filled past the given percentage
+    /// of its limit
+    pub fn with_health_buffer_threshold_percent(mut self, percent: u8) -> Self {
+        self.health_buffer_threshold_percent = Some(percent.to_string());
+        self
+    }
+
+    /// Set the maximum size, in bytes, of HTTP request bodies
+    pub fn with_max_http_request_size(mut self, size: usize) -> Self {
+        self.max_http_request_size = Some(size.to_string());
+        self
+    }
+
     /// Spawn a new [`TestServer`] with this configuration
     ///
     /// This will run the `influxdb3 serve` command, and bind its HTTP
@@ -51,6 +126,33 @@ impl TestConfig {
         if let Some((token, _)) = &self.auth_token {
             args.append(&mut vec!["--bearer-token", token]);
         }
+        if let Some(rules) = &self.disable_authz {
+            args.append(&mut vec!["--disable-authz", rules]);
+        }
+        if let Some(db) = &self.default_db {
+            args.append(&mut vec!["--default-db", db]);
+        }
+        if let Some(limit) = &self.rate_limit_per_token {
+            args.append(&mut vec!["--rate-limit-per-token", limit]);
+        }
+        if let Some(name) = &self.auth_header_name {
+            args.append(&mut vec!["--auth-header-name", name]);
+        }
+        if let Some(limit) = &self.max_chunks_per_query {
+            args.append(&mut vec!["--max-chunks-per-query", limit]);
+        }
+        if self.canonical_column_order {
+            args.push("--canonical-column-order");
+        }
+        if let Some(limit) = &self.buffer_mem_limit_mb {
+            args.append(&mut vec!["--buffer-mem-limit-mb", limit]);
+        }
+        if let Some(percent) = &self.health_buffer_threshold_percent {
+            args.append(&mut vec!["--health-buffer-threshold-percent", percent]);
+        }
+        if let Some(size) = &self.max_http_request_size {
+            args.append(&mut vec!["--max-http-request-size", size]);
+        }
         args
     }
 }
@@ -1,4 +1,5 @@
-use hyper::Method;
+use hyper::{Method, StatusCode};
+use influxdb3_client::Precision;
 use serde_json::Value;
 
 use crate::TestServer;
@@ -34,9 +35,157 @@ async fn test_ping() {
             .unwrap();
         let json = resp.json::<Value>().await.unwrap();
         println!("Method: {}, URL: {}", t.method, t.url);
-        println!("{json:#}");
         let map = json.as_object().unwrap();
         assert!(map.contains_key("version"));
         assert!(map.contains_key("revision"));
     }
 }
+
+#[tokio::test]
+async fn test_capabilities() {
+    let server = TestServer::spawn().await;
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+
+    let capabilities = client.api_v3_capabilities().await.unwrap();
+
+    assert_eq!(capabilities.write().formats(), ["line_protocol"]);
+    assert_eq!(
+        capabilities.write().precisions(),
+        ["auto", "second", "millisecond", "microsecond", "nanosecond"]
+    );
+    assert_eq!(
+        capabilities.query().formats(),
+        ["json", "jsonl", "csv", "pretty", "parquet", "arrow"]
+    );
+    assert_eq!(capabilities.query().languages(), ["sql", "influxql"]);
+    assert!(capabilities
+        .query()
+        .endpoints()
+        .contains(&"/api/v3/query_sql".to_string()));
+}
+
+#[tokio::test]
+async fn test_health_verbose() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db("foo", "cpu,host=a usage=0.5 1", Precision::Nanosecond)
+        .await
+        .unwrap();
+    let client = reqwest::Client::new();
+
+    for path in ["health", "api/v1/health"] {
+        let url = format!("{base}/{path}", base = server.client_addr());
+
+        // A plain health check stays a bare OK:
+        let resp = client.get(&url).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.text().await.unwrap(), "OK");
+
+        let resp = client
+            .get(&url)
+            .query(&[("verbose", "true")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let json = resp.json::<Value>().await.unwrap();
+        assert_eq!(json["status"], "ok");
+        assert!(!json["version"].as_str().unwrap().is_empty());
+        assert!(json["uptime_seconds"].is_u64());
+        assert_eq!(json["catalog"]["databases"], 1);
+        // The test server runs without a WAL:
+        assert_eq!(json["checks"]["wal"]["status"], "disabled");
+        assert_eq!(json["checks"]["write_buffer"]["status"], "ok");
+        assert_eq!(json["checks"]["object_store"]["status"], "ok");
+
+        let resp = client
+            .get(&url)
+            .query(&[("verbose", "yes")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_ping_build_metadata() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .with_disable_authz(["ping"])
+        .spawn()
+        .await;
+    // No token is given, as authorization is disabled for /ping:
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+
+    let ping = client.ping().await.unwrap();
+    assert!(!ping.version().is_empty());
+    assert!(ping.git_commit().is_some_and(|commit| !commit.is_empty()));
+    assert!(ping.build_date().is_some());
+    assert_eq!(ping.api_versions(), ["v1", "v2", "v3"]);
+}
+
+#[tokio::test]
+async fn test_client_health_and_ping() {
+    const HASHED_TOKEN: &str = "5315f0c4714537843face80cca8c18e27ce88e31e9be7a5232dc4dc8444f27c0227a9bd64831d3ab58f652bd0262dd8558dd08870ac9e5c650972ce9e4259439";
+    const TOKEN: &str = "apiv3_mp75KQAhbqv0GeQXk8MPuZ3ztaLEaR5JzS8iifk1FwuroSVyXXyrJK1c4gEr1kHkmbgzDV-j3MvQpaIMVJBAiA";
+    let server = TestServer::configure()
+        .auth_token(HASHED_TOKEN, TOKEN)
+        .with_disable_authz(["health", "ping"])
+        .spawn()
+        .await;
+    // No token is given, as authorization is disabled for both /health and /ping:
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+
+    let ping = client.ping().await.unwrap();
+    assert!(!ping.version().is_empty());
+
+    let health = client.health().await.unwrap();
+    assert!(health.is_ok());
+    assert_eq!(health.status(), "ok");
+    assert!(health.message().is_none());
+    assert_eq!(health.version(), ping.version());
+    assert_eq!(health.revision(), ping.revision());
+    assert_eq!(health.catalog().databases(), 0);
+    assert_eq!(health.checks().wal().status(), "disabled");
+    assert!(health.checks().write_buffer().limit_bytes() > 0);
+    assert_eq!(health.checks().object_store().status(), "ok");
+}
+
+#[tokio::test]
+async fn test_metrics_formats() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/metrics", base = server.client_addr());
+
+    // Prometheus text is served by default, for existing scrapers:
+    let resp = client.get(&url).send().await.unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/plain; version=0.0.4; charset=utf-8"
+    );
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("# TYPE "));
+    assert!(!body.contains("# EOF"));
+
+    let resp = client
+        .get(&url)
+        .header(
+            "Accept",
+            "application/openmetrics-text; version=1.0.0, text/plain; q=0.5",
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "application/openmetrics-text; version=1.0.0; charset=utf-8"
+    );
+    let body = resp.text().await.unwrap();
+    assert!(body.contains("# TYPE "));
+    assert!(body.ends_with("# EOF\n"), "unexpected body: {body}");
+}
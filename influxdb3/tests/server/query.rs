@@ -1,7 +1,11 @@
+use std::io::Read;
+
 use crate::TestServer;
-use futures::StreamExt;
+use flate2::read::GzDecoder;
+use futures::{StreamExt, TryStreamExt};
 use influxdb3_client::Precision;
 use pretty_assertions::assert_eq;
+use reqwest::StatusCode;
 use serde_json::{json, Value};
 use test_helpers::assert_contains;
 
@@ -51,6 +55,429 @@ async fn api_v3_query_sql() {
     );
 }
 
+#[tokio::test]
+async fn api_v3_query_sql_select_star_column_order() {
+    async fn write(server: &TestServer) {
+        // Fields and tags arrive in different orders on each write:
+        server
+            .write_lp_to_db(
+                "foo",
+                "cpu,region=us-east,host=s1 usage=0.9,temp=40i 1
+                cpu,host=s1,region=us-east temp=41i,usage=0.89 2",
+                Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+        server
+            .write_lp_to_db(
+                "foo",
+                "cpu,region=us-east,host=s1 usage=0.85,load=1.5,temp=42i 3",
+                Precision::Nanosecond,
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn select_star(server: &TestServer) -> String {
+        server
+            .api_v3_query_sql(&[
+                ("db", "foo"),
+                ("q", "SELECT * FROM cpu ORDER BY time"),
+                ("format", "pretty"),
+            ])
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    // By default, columns are sorted by name:
+    let server = TestServer::spawn().await;
+    write(&server).await;
+    assert_eq!(
+        "+------+------+---------+------+-------------------------------+-------+\n\
+        | host | load | region  | temp | time                          | usage |\n\
+        +------+------+---------+------+-------------------------------+-------+\n\
+        | s1   |      | us-east | 40   | 1970-01-01T00:00:00.000000001 | 0.9   |\n\
+        | s1   |      | us-east | 41   | 1970-01-01T00:00:00.000000002 | 0.89  |\n\
+        | s1   | 1.5  | us-east | 42   | 1970-01-01T00:00:00.000000003 | 0.85  |\n\
+        +------+------+---------+------+-------------------------------+-------+",
+        select_star(&server).await,
+    );
+
+    // With the option, tags come first, then fields, then time:
+    let server = TestServer::configure()
+        .with_canonical_column_order()
+        .spawn()
+        .await;
+    write(&server).await;
+    assert_eq!(
+        "+------+---------+------+------+-------+-------------------------------+\n\
+        | host | region  | load | temp | usage | time                          |\n\
+        +------+---------+------+------+-------+-------------------------------+\n\
+        | s1   | us-east |      | 40   | 0.9   | 1970-01-01T00:00:00.000000001 |\n\
+        | s1   | us-east |      | 41   | 0.89  | 1970-01-01T00:00:00.000000002 |\n\
+        | s1   | us-east | 1.5  | 42   | 0.85  | 1970-01-01T00:00:00.000000003 |\n\
+        +------+---------+------+------+-------+-------------------------------+",
+        select_star(&server).await,
+    );
+
+    // Projecting a subset of the columns is unaffected by the reordering:
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT usage, host FROM cpu WHERE load IS NOT NULL"),
+            ("format", "json"),
+        ])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(resp, json!([{"usage": 0.85, "host": "s1"}]));
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_json_schema() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1 usage=0.9,count=3i 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let query = "SELECT host, usage, count * 2 AS double_count, time FROM cpu";
+
+    // The default response is unchanged:
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", "json")])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(
+        resp,
+        json!([{
+            "host": "s1",
+            "usage": 0.9,
+            "double_count": 6,
+            "time": "1970-01-01T00:00:00.000000001"
+        }])
+    );
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", query),
+            ("format", "json"),
+            ("schema", "true"),
+        ])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(
+        resp,
+        json!({
+            "schema": {
+                "columns": [
+                    {
+                        "name": "host",
+                        "type": "Dictionary(Int32, Utf8)",
+                        "influx_type": "tag"
+                    },
+                    { "name": "usage", "type": "Float64", "influx_type": "field" },
+                    { "name": "double_count", "type": "Int64", "influx_type": null },
+                    {
+                        "name": "time",
+                        "type": "Timestamp(Nanosecond, None)",
+                        "influx_type": "timestamp"
+                    }
+                ]
+            },
+            "data": [{
+                "host": "s1",
+                "usage": 0.9,
+                "double_count": 6,
+                "time": "1970-01-01T00:00:00.000000001"
+            }]
+        })
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_formats() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1 usage=0.9 1\ncpu,host=s2 usage=0.8 2",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let query = "SELECT host, usage FROM cpu ORDER BY host";
+
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", "csv")])
+        .await;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/csv");
+    assert_eq!(resp.text().await.unwrap(), "host,usage\ns1,0.9\ns2,0.8\n");
+
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", "json")])
+        .await;
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!([
+            { "host": "s1", "usage": 0.9 },
+            { "host": "s2", "usage": 0.8 }
+        ])
+    );
+
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", "parquet")])
+        .await;
+    let bytes = resp.bytes().await.unwrap();
+    assert!(bytes.starts_with(b"PAR1") && bytes.ends_with(b"PAR1"));
+
+    // The Accept header is used when no format parameter is given:
+    let resp = reqwest::Client::new()
+        .get(format!(
+            "{base}/api/v3/query_sql",
+            base = server.client_addr()
+        ))
+        .query(&[("db", "foo"), ("q", query)])
+        .header("accept", "text/csv")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.text().await.unwrap(), "host,usage\ns1,0.9\ns2,0.8\n");
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_arrow() {
+    let server = TestServer::spawn().await;
+
+    // Write data spanning several hours, so that the query produces several record batches:
+    let lp = (0..30).fold(String::new(), |mut acc, i| {
+        acc.push_str(&format!("cpu,host=s{} usage=0.5 {}\n", i % 3, i * 600));
+        acc
+    });
+    server
+        .write_lp_to_db("foo", lp, Precision::Second)
+        .await
+        .unwrap();
+
+    let query = "SELECT host, time, usage FROM cpu ORDER BY time";
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", "arrow")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "application/vnd.apache.arrow.stream"
+    );
+    let bytes = resp.bytes().await.unwrap();
+    let reader = arrow::ipc::reader::StreamReader::try_new(bytes.as_ref(), None).unwrap();
+    let schema = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, _>>().unwrap();
+    let names = schema
+        .fields()
+        .iter()
+        .map(|f| f.name().as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["host", "time", "usage"]);
+
+    // The decoded batches hold the same rows as the JSON response:
+    let mut writer = arrow::json::ArrayWriter::new(Vec::new());
+    for batch in &batches {
+        writer.write(batch).unwrap();
+    }
+    writer.finish().unwrap();
+    let decoded: Value = serde_json::from_slice(&writer.into_inner()).unwrap();
+
+    let json = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", query), ("format", "json")])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    assert_eq!(decoded.as_array().unwrap().len(), 30);
+    assert_eq!(decoded, json);
+
+    // The Accept header selects the Arrow format when no format parameter is given:
+    let resp = reqwest::Client::new()
+        .get(format!(
+            "{base}/api/v3/query_sql",
+            base = server.client_addr()
+        ))
+        .query(&[("db", "foo"), ("q", query)])
+        .header("accept", "application/vnd.apache.arrow.stream")
+        .send()
+        .await
+        .unwrap();
+    let bytes = resp.bytes().await.unwrap();
+    let reader = arrow::ipc::reader::StreamReader::try_new(bytes.as_ref(), None).unwrap();
+    assert_eq!(
+        reader.map(|batch| batch.unwrap().num_rows()).sum::<usize>(),
+        30
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_jsonl_streamed() {
+    let server = TestServer::spawn().await;
+
+    // Write data spanning several hours, so that it is buffered in several segments,
+    // and the query produces several record batches:
+    let lp = (0..300).fold(String::new(), |mut acc, i| {
+        acc.push_str(&format!("cpu,host=s{} usage=0.5 {}\n", i % 10, i * 60));
+        acc
+    });
+    server
+        .write_lp_to_db("foo", lp, Precision::Second)
+        .await
+        .unwrap();
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu"),
+            ("format", "jsonl"),
+        ])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    // The response is streamed, so its length is not known up front:
+    assert!(resp.headers().get("content-length").is_none());
+    assert_eq!(resp.headers().get("transfer-encoding").unwrap(), "chunked");
+
+    let mut chunks = resp.bytes_stream();
+    let mut body = Vec::new();
+    while let Some(chunk) = chunks.next().await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+    let rows = std::str::from_utf8(&body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .count();
+    assert_eq!(rows, 300);
+}
+
+#[tokio::test]
+async fn client_stream_rows_matches_collected_query() {
+    let server = TestServer::spawn().await;
+
+    // Write data spanning several hours, so that the query produces several record batches:
+    let lp = (0..300).fold(String::new(), |mut acc, i| {
+        acc.push_str(&format!("cpu,host=s{} usage=0.5 {}\n", i % 10, i * 60));
+        acc
+    });
+    server
+        .write_lp_to_db("foo", lp, Precision::Second)
+        .await
+        .unwrap();
+
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+    let query = "SELECT host, usage, time FROM cpu ORDER BY time";
+
+    let streamed = client
+        .api_v3_query_sql("foo", query)
+        .stream_rows::<Value>()
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    let collected = client
+        .api_v3_query_sql("foo", query)
+        .format(influxdb3_client::Format::Json)
+        .send()
+        .await
+        .unwrap();
+    let collected = serde_json::from_slice::<Vec<Value>>(&collected).unwrap();
+
+    assert_eq!(streamed.len(), 300);
+    assert_eq!(streamed, collected);
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_timeout() {
+    let server = TestServer::spawn().await;
+
+    let lp = (0..1000).fold(String::new(), |mut acc, i| {
+        acc.push_str(&format!("cpu,host=s{i} usage=0.5 {i}\n"));
+        acc
+    });
+    server
+        .write_lp_to_db("foo", lp, Precision::Second)
+        .await
+        .unwrap();
+
+    // A cross join of the table with itself produces far too many rows to count within
+    // the timeout:
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT count(*) FROM cpu a, cpu b, cpu c, cpu d"),
+            ("timeout", "100ms"),
+        ])
+        .await;
+    assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({ "error": "query exceeded the timeout of 100ms", "data": null })
+    );
+
+    // The JSON lines and Arrow formats are streamed, so the response may have started by
+    // the time the timeout is reached, in which case it is aborted rather than completed:
+    for format in ["jsonl", "arrow"] {
+        let resp = server
+            .api_v3_query_sql(&[
+                ("db", "foo"),
+                ("q", "SELECT a.host FROM cpu a, cpu b, cpu c"),
+                ("format", format),
+                ("timeout", "500ms"),
+            ])
+            .await;
+        if resp.status() == StatusCode::OK {
+            assert!(
+                resp.bytes().await.is_err(),
+                "the {format} response was not aborted"
+            );
+        } else {
+            assert_eq!(resp.status(), StatusCode::REQUEST_TIMEOUT);
+        }
+    }
+
+    // A query that completes within the timeout is unaffected:
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT count(*) AS n FROM cpu"),
+            ("timeout", "30s"),
+        ])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.json::<Value>().await.unwrap(), json!([{ "n": 1000 }]));
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT count(*) FROM cpu"),
+            ("timeout", "soon"),
+        ])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
 #[tokio::test]
 async fn api_v3_query_sql_params() {
     let server = TestServer::spawn().await;
@@ -170,6 +597,153 @@ async fn api_v3_query_sql_params() {
     }
 }
 
+#[tokio::test]
+async fn api_v3_query_sql_malformed_body() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=s1 usage=0.9 1", Precision::Nanosecond)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+
+    let resp = client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .body(r#"{"db":"foo","q":"SELECT usage FROM cpu",}"#)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = resp.json::<Value>().await.unwrap();
+    let error = body["error"].as_str().unwrap();
+    assert!(
+        error.starts_with("invalid request body: trailing comma at line 1 column"),
+        "unexpected error: {error}"
+    );
+
+    // The JSON params of a GET request are validated the same way:
+    let resp = client
+        .get(&url)
+        .query(&[
+            ("db", "foo"),
+            ("q", "SELECT usage FROM cpu WHERE host = $host"),
+            ("params", r#"{"host":"s1""#),
+        ])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = resp.json::<Value>().await.unwrap();
+    let error = body["error"].as_str().unwrap();
+    assert!(
+        error.starts_with("invalid params parameter: EOF while parsing an object"),
+        "unexpected error: {error}"
+    );
+
+    let resp = client
+        .post(&url)
+        .json(&json!({
+            "db": "foo",
+            "q": "SELECT usage FROM cpu WHERE host = $host",
+            "params": { "host": "s1" },
+            "format": "json"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!([{ "usage": 0.9 }])
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_gzip_response() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1 usage=0.9 1\ncpu,host=s2 usage=0.8 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/query_sql", base = server.client_addr());
+    // Streamed formats, such as JSON lines, are compressed as they are streamed:
+    for format in ["json", "jsonl"] {
+        let params = &[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu ORDER BY host"),
+            ("format", format),
+        ];
+
+        let resp = client.get(&url).query(params).send().await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().get("content-encoding").is_none());
+        let uncompressed = resp.text().await.unwrap();
+
+        let resp = client
+            .get(&url)
+            .query(params)
+            .header("accept-encoding", "gzip")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("content-encoding").unwrap(), "gzip");
+        let compressed = resp.bytes().await.unwrap();
+
+        let mut decompressed = String::new();
+        GzDecoder::new(compressed.as_ref())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(uncompressed, decompressed);
+    }
+}
+
+#[tokio::test]
+async fn api_v3_query_sql_undefined_column() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9 1",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", "SELECT host, usge FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({
+            "error": "column 'usge' does not exist, did you mean 'usage'?",
+            "data": null
+        })
+    );
+
+    // Columns with no close match are still reported, without a suggestion:
+    let resp = server
+        .api_v3_query_sql(&[("db", "foo"), ("q", "SELECT temperature FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({ "error": "column 'temperature' does not exist", "data": null })
+    );
+}
+
 #[tokio::test]
 async fn api_v3_query_influxql() {
     let server = TestServer::spawn().await;
@@ -390,6 +964,143 @@ async fn api_v3_query_influxql() {
     }
 }
 
+#[tokio::test]
+async fn api_v3_query_missing_db() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=s1 usage=0.9 1", Precision::Nanosecond)
+        .await
+        .unwrap();
+
+    let resp = server
+        .api_v3_query_sql(&[("q", "SELECT host, usage FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({ "error": "missing required parameter: db", "data": null })
+    );
+
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SELECT host, usage FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!({
+            "error": "must specify a 'db' parameter, or provide the database in the InfluxQL query",
+            "data": null
+        })
+    );
+
+    // A database given in the InfluxQL query does not need the parameter:
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SELECT host, usage FROM foo.autogen.cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn api_v3_query_default_db() {
+    let server = TestServer::configure().with_default_db("foo").spawn().await;
+
+    server
+        .write_lp_to_db("foo", "cpu,host=s1 usage=0.9 1", Precision::Nanosecond)
+        .await
+        .unwrap();
+    server
+        .write_lp_to_db("bar", "cpu,host=s2 usage=0.1 1", Precision::Nanosecond)
+        .await
+        .unwrap();
+
+    let resp = server
+        .api_v3_query_sql(&[("q", "SELECT host, usage FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!([{ "host": "s1", "usage": 0.9 }])
+    );
+
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SELECT host, usage FROM cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!([{
+            "iox::measurement": "cpu",
+            "time": "1970-01-01T00:00:00.000000001",
+            "host": "s1",
+            "usage": 0.9
+        }])
+    );
+
+    // A database given in the InfluxQL query takes precedence over the default:
+    let resp = server
+        .api_v3_query_influxql(&[("q", "SELECT host, usage FROM bar.autogen.cpu")])
+        .await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.json::<Value>().await.unwrap(),
+        json!([{
+            "iox::measurement": "cpu",
+            "time": "1970-01-01T00:00:00.000000001",
+            "host": "s2",
+            "usage": 0.1
+        }])
+    );
+}
+
+#[tokio::test]
+async fn api_v3_query_influxql_matches_sql() {
+    let server = TestServer::spawn().await;
+
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1 usage=0.9 1\n\
+            cpu,host=s2 usage=0.8 2\n\
+            cpu,host=s3 usage=0.7 3",
+            Precision::Nanosecond,
+        )
+        .await
+        .unwrap();
+
+    let usage = |rows: Value| -> Vec<Value> {
+        rows.as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row["usage"].clone())
+            .collect()
+    };
+
+    let sql = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT usage FROM cpu ORDER BY time"),
+            ("format", "json"),
+        ])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+    let influxql = server
+        .api_v3_query_influxql(&[
+            ("db", "foo"),
+            ("q", "SELECT usage FROM cpu"),
+            ("format", "json"),
+        ])
+        .await
+        .json::<Value>()
+        .await
+        .unwrap();
+
+    assert_eq!(usage(sql), vec![json!(0.9), json!(0.8), json!(0.7)]);
+    assert_eq!(usage(influxql), vec![json!(0.9), json!(0.8), json!(0.7)]);
+}
+
 #[tokio::test]
 async fn api_v3_query_influxql_params() {
     let server = TestServer::spawn().await;
@@ -1168,3 +1879,28 @@ async fn api_v1_query_chunked() {
         assert_eq!(t.expected, values, "query failed: {q}", q = t.query);
     }
 }
+
+#[tokio::test]
+async fn client_query_csv() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=s1,region=us-east usage=0.9 1\n\
+            cpu,host=s2,region=us-east usage=0.8 2\n\
+            cpu,host=s3,region=us-west usage=0.7 3",
+            Precision::Second,
+        )
+        .await
+        .unwrap();
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+
+    let csv = client
+        .query_csv("foo", "SELECT host, region, usage FROM cpu ORDER BY host")
+        .await
+        .unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("host,region,usage"));
+    assert_eq!(lines.next(), Some("s1,us-east,0.9"));
+    assert_eq!(lines.count(), 2);
+}
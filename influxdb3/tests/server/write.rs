@@ -1,3 +1,6 @@
+use std::io::Write;
+
+use flate2::{write::GzEncoder, Compression};
 use hyper::StatusCode;
 use influxdb3_client::Precision;
 use pretty_assertions::assert_eq;
@@ -257,6 +260,49 @@ async fn api_v1_write_round_trip() {
     );
 }
 
+#[tokio::test]
+async fn api_v1_write_consistency() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let write_url = format!("{base}/write", base = server.client_addr());
+
+    for (consistency, expected) in [
+        ("any", StatusCode::OK),
+        ("one", StatusCode::OK),
+        ("quorum", StatusCode::OK),
+        ("all", StatusCode::OK),
+        ("most", StatusCode::BAD_REQUEST),
+    ] {
+        let resp = client
+            .post(&write_url)
+            .query(&[("db", "foo"), ("consistency", consistency)])
+            .body("cpu,host=a usage=0.5 1")
+            .send()
+            .await
+            .expect("send /write request");
+        assert_eq!(expected, resp.status(), "consistency: {consistency}");
+    }
+
+    let resp = server
+        .api_v3_query_influxql(&[
+            ("q", "SELECT time, host, usage FROM foo.autogen.cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        resp,
+        "+------------------+-------------------------------+------+-------+\n\
+        | iox::measurement | time                          | host | usage |\n\
+        +------------------+-------------------------------+------+-------+\n\
+        | cpu              | 1970-01-01T00:00:00.000000001 | a    | 0.5   |\n\
+        +------------------+-------------------------------+------+-------+"
+    );
+}
+
 #[tokio::test]
 async fn api_v2_write_request_parsing() {
     let server = TestServer::spawn().await;
@@ -429,3 +475,492 @@ async fn writes_with_different_schema_should_fail() {
         "the request should hae failed with an API Error"
     );
 }
+
+#[tokio::test]
+async fn api_v3_write_lp_missing_db() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!(
+            "{base}/api/v3/write_lp",
+            base = server.client_addr()
+        ))
+        .body("cpu,host=a usage=0.5")
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.text().await.unwrap(),
+        r#"{"error":"missing required parameter: db","data":null}"#
+    );
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_default_db() {
+    let server = TestServer::configure().with_default_db("foo").spawn().await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!(
+            "{base}/api/v3/write_lp",
+            base = server.client_addr()
+        ))
+        .body("cpu,host=a usage=0.5 1")
+        .send()
+        .await
+        .expect("send write request");
+    assert!(resp.status().is_success());
+
+    // The write went to the default database:
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .expect("get body");
+    assert_eq!(
+        "\
+        +------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | a    | 0.5   |\n\
+        +------+-------+",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_summary() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+    let lp = "cpu,host=a usage=0.5 1\ncpu,host=b usage=0.7 1\nmem,host=a used=12 1";
+
+    // By default, the response has no body:
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .body(lp)
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text().await.unwrap(), "");
+
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo"), ("summary", "true")])
+        .body(lp)
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({ "lines_written": 3, "bytes": lp.len() })
+    );
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_request_size_limit() {
+    let server = TestServer::configure()
+        .with_max_http_request_size(64)
+        .spawn()
+        .await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+
+    let under = "cpu,host=a usage=0.5 1\ncpu,host=b usage=0.7 1";
+    assert!(under.len() < 64);
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .body(under)
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let over = "cpu,host=a usage=0.5 1\n".repeat(4);
+    assert!(over.len() > 64);
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .body(over)
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(
+        resp.text().await.unwrap(),
+        r#"{"error":"max request size (64 bytes) exceeded","data":null}"#
+    );
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_content_type() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .header("content-type", "application/json")
+        .body(r#"{"measurement":"cpu","fields":{"usage":0.5}}"#)
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_contains!(
+        resp.text().await.unwrap(),
+        "unsupported content type for a line protocol write (application/json)"
+    );
+
+    // Line protocol sent as plain text, with no content type, or as form data, as curl
+    // does by default, is accepted:
+    for content_type in [
+        Some("text/plain; charset=utf-8"),
+        None,
+        Some("application/x-www-form-urlencoded"),
+    ] {
+        let mut req = client
+            .post(&url)
+            .query(&[("db", "foo")])
+            .body("cpu,host=a usage=0.5 1");
+        if let Some(content_type) = content_type {
+            req = req.header("content-type", content_type);
+        }
+        let resp = req.send().await.expect("send write request");
+        assert_eq!(
+            resp.status(),
+            StatusCode::OK,
+            "content type: {content_type:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_precision() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo"), ("precision", "decades")])
+        .body("cpu,host=a usage=0.5 1")
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        resp.json::<serde_json::Value>().await.unwrap(),
+        serde_json::json!({
+            "error": "invalid write parameters: invalid precision 'decades', expected one of: \
+                auto, ns, us, µs, ms, s, nanosecond, microsecond, millisecond, second",
+            "data": null
+        })
+    );
+
+    // Both the full names and the abbreviations are accepted:
+    for (precision, host) in [
+        ("s", "a"),
+        ("second", "b"),
+        ("ms", "c"),
+        ("millisecond", "d"),
+        ("us", "e"),
+        ("microsecond", "f"),
+        ("ns", "g"),
+        ("nanosecond", "h"),
+    ] {
+        let resp = client
+            .post(&url)
+            .query(&[("db", "foo"), ("precision", precision)])
+            .body(format!("cpu,host={host} usage=0.5 1"))
+            .send()
+            .await
+            .expect("send write request");
+        assert_eq!(resp.status(), StatusCode::OK, "precision: {precision}");
+    }
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, time FROM cpu ORDER BY host"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .expect("get body");
+    assert_eq!(
+        "\
+        +------+-------------------------------+\n\
+        | host | time                          |\n\
+        +------+-------------------------------+\n\
+        | a    | 1970-01-01T00:00:01           |\n\
+        | b    | 1970-01-01T00:00:01           |\n\
+        | c    | 1970-01-01T00:00:00.001       |\n\
+        | d    | 1970-01-01T00:00:00.001       |\n\
+        | e    | 1970-01-01T00:00:00.000001    |\n\
+        | f    | 1970-01-01T00:00:00.000001    |\n\
+        | g    | 1970-01-01T00:00:00.000000001 |\n\
+        | h    | 1970-01-01T00:00:00.000000001 |\n\
+        +------+-------------------------------+",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_error_line_numbers() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+
+    // Line 3 has an unterminated string:
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo"), ("accept_partial", "false")])
+        .body(
+            "cpu,host=a usage=0.5 1\n\
+            cpu,host=b usage=0.6 2\n\
+            cpu,host=c usage=0.7,note=\"unterminated 3",
+        )
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = resp.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["error"], "parsing failed for write_lp endpoint");
+    assert_eq!(body["data"]["line_number"], 3);
+    assert_eq!(
+        body["data"]["original_line"],
+        "cpu,host=c usage=0.7,note=\"unterminated 3"
+    );
+    // Parsing stopped at the comma before the unterminated field:
+    assert_eq!(body["data"]["column"], 21);
+
+    // Line numbers count the blank lines and comments in the request, and invalid lines
+    // that are skipped by a partial write do not shift the lines reported after them:
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .body(
+            "cpu,host=a usage=0.5 1\n\
+            \n\
+            # a comment\n\
+            cpu,host=b usage=\"wrong type\" 2\n\
+            cpu,host=c usage=0.7 3\n\
+            cpu,host=d usage= 4",
+        )
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body = resp.json::<serde_json::Value>().await.unwrap();
+    assert_eq!(body["error"], "partial write of line protocol occurred");
+    assert_eq!(body["data"][0]["line_number"], 4);
+    assert_eq!(
+        body["data"][0]["original_line"],
+        "cpu,host=b usage=\"wrong type\" 2"
+    );
+    assert_contains!(
+        body["data"][0]["error_message"].as_str().unwrap(),
+        "on line 4"
+    );
+    assert_eq!(body["data"][1]["line_number"], 6);
+    assert_eq!(body["data"][1]["original_line"], "cpu,host=d usage= 4");
+    // Lines that fail validation, or that the parser cannot say where it stopped in, have no
+    // column:
+    assert!(body["data"][0].get("column").is_none());
+    assert!(body["data"][1].get("column").is_none());
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_microsecond_round_trip() {
+    let server = TestServer::spawn().await;
+
+    // Write with the client's microsecond precision:
+    server
+        .write_lp_to_db(
+            "foo",
+            "cpu,host=a usage=0.5 1500001",
+            Precision::Microsecond,
+        )
+        .await
+        .unwrap();
+
+    // Write with each spelling of microseconds accepted by the precision parameter:
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+    for (precision, host) in [("us", "b"), ("µs", "c")] {
+        let resp = client
+            .post(&url)
+            .query(&[("db", "foo"), ("precision", precision)])
+            .body(format!("cpu,host={host} usage=0.5 1500001"))
+            .send()
+            .await
+            .expect("send write request");
+        assert_eq!(resp.status(), StatusCode::OK, "precision: {precision}");
+    }
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            (
+                "q",
+                "SELECT host, CAST(time AS BIGINT) AS nanos FROM cpu ORDER BY host",
+            ),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .expect("get body");
+    assert_eq!(
+        "\
+        +------+------------+\n\
+        | host | nanos      |\n\
+        +------+------------+\n\
+        | a    | 1500001000 |\n\
+        | b    | 1500001000 |\n\
+        | c    | 1500001000 |\n\
+        +------+------------+",
+        resp
+    );
+}
+
+#[tokio::test]
+async fn api_v3_write_lp_gzip() {
+    let server = TestServer::spawn().await;
+    let client = reqwest::Client::new();
+    let url = format!("{base}/api/v3/write_lp", base = server.client_addr());
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(b"cpu,host=a usage=0.5 1\ncpu,host=b usage=0.7 1\n")
+        .unwrap();
+    let body = encoder.finish().unwrap();
+
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .header("content-encoding", "gzip")
+        .body(body)
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = server
+        .api_v3_query_sql(&[
+            ("db", "foo"),
+            ("q", "SELECT host, usage FROM cpu ORDER BY host"),
+            ("format", "pretty"),
+        ])
+        .await
+        .text()
+        .await
+        .expect("get body");
+    assert_eq!(
+        "\
+        +------+-------+\n\
+        | host | usage |\n\
+        +------+-------+\n\
+        | a    | 0.5   |\n\
+        | b    | 0.7   |\n\
+        +------+-------+",
+        resp
+    );
+
+    // A body that is not valid gzip is rejected:
+    let resp = client
+        .post(&url)
+        .query(&[("db", "foo")])
+        .header("content-encoding", "gzip")
+        .body("cpu,host=c usage=0.9 1")
+        .send()
+        .await
+        .expect("send write request");
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    assert_contains!(resp.text().await.unwrap(), "error decoding gzip stream");
+}
+
+#[tokio::test]
+async fn client_write_batcher() {
+    let server = TestServer::spawn().await;
+    server
+        .write_lp_to_db("foo", "cpu,host=s0 usage=0.5 0", Precision::Second)
+        .await
+        .unwrap();
+
+    let client = influxdb3_client::Client::new(server.client_addr()).unwrap();
+    let mut batcher = client
+        .write_batcher("foo")
+        .precision(Precision::Second)
+        .max_lines(1_000)
+        .flush_interval(std::time::Duration::from_secs(3600));
+    for i in 1..100 {
+        batcher
+            .write(format!("cpu,host=s{i} usage=0.5 {i}"))
+            .await
+            .unwrap();
+    }
+    assert_eq!(batcher.buffered_lines(), 99);
+
+    let count = || async {
+        server
+            .api_v3_query_sql(&[
+                ("db", "foo"),
+                ("q", "SELECT count(*) AS count FROM cpu"),
+                ("format", "json"),
+            ])
+            .await
+            .json::<serde_json::Value>()
+            .await
+            .unwrap()
+    };
+
+    // Nothing has been sent below the batch size:
+    assert_eq!(count().await, serde_json::json!([{"count": 1}]));
+
+    batcher.flush().await.unwrap();
+    assert_eq!(batcher.buffered_lines(), 0);
+    assert_eq!(count().await, serde_json::json!([{"count": 100}]));
+
+    // A full batch is sent without an explicit flush:
+    let mut batcher = client
+        .write_batcher("foo")
+        .precision(Precision::Second)
+        .max_lines(10);
+    for i in 100..110 {
+        batcher
+            .write(format!("cpu,host=s{i} usage=0.5 {i}"))
+            .await
+            .unwrap();
+    }
+    assert_eq!(batcher.buffered_lines(), 0);
+    assert_eq!(count().await, serde_json::json!([{"count": 110}]));
+
+    // A batch that has waited for the flush interval is sent without another write:
+    let mut batcher = client
+        .write_batcher("foo")
+        .precision(Precision::Second)
+        .flush_interval(std::time::Duration::from_millis(100));
+    batcher.write("cpu,host=s110 usage=0.5 110").await.unwrap();
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        while count().await != serde_json::json!([{"count": 111}]) {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("batch is sent after the flush interval");
+    assert_eq!(batcher.buffered_lines(), 0);
+}
@@ -1,10 +1,19 @@
-use std::{collections::HashMap, fmt::Display, string::FromUtf8Error};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::Write,
+    string::FromUtf8Error,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
 
 use bytes::Bytes;
+use futures::{Stream, StreamExt};
 use iox_query_params::StatementParam;
-use reqwest::{Body, IntoUrl, StatusCode};
+use reqwest::{header::CONTENT_ENCODING, Body, IntoUrl, StatusCode};
 use secrecy::{ExposeSecret, Secret};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::sync::{Mutex as AsyncMutex, Notify};
 use url::Url;
 
 /// Primary error type for the [`Client`]
@@ -13,15 +22,36 @@ pub enum Error {
     #[error("base URL error: {0}")]
     BaseUrl(#[source] reqwest::Error),
 
+    #[error("invalid root certificate: {0}")]
+    RootCert(#[source] reqwest::Error),
+
+    #[error("failed to build the HTTP client: {0}")]
+    HttpClient(#[source] reqwest::Error),
+
     #[error("request URL error: {0}")]
     RequestUrl(#[from] url::ParseError),
 
     #[error("failed to send /api/v3/write_lp request: {0}")]
     WriteLpSend(#[source] reqwest::Error),
 
+    #[error("failed to gzip the /api/v3/write_lp request body: {0}")]
+    WriteLpGzip(#[source] std::io::Error),
+
+    #[error(
+        "a streaming body cannot be compressed or retried, \
+        use a body that is held in memory instead"
+    )]
+    WriteLpStreamingBody,
+
     #[error("failed to send /ping request: {0}")]
     PingSend(#[source] reqwest::Error),
 
+    #[error("failed to send /health request: {0}")]
+    HealthSend(#[source] reqwest::Error),
+
+    #[error("failed to send /api/v3/capabilities request: {0}")]
+    CapabilitiesSend(#[source] reqwest::Error),
+
     #[error("failed to read the API response bytes: {0}")]
     Bytes(#[source] reqwest::Error),
 
@@ -51,12 +81,53 @@ pub enum Error {
     #[error("failed to parse plaintext response: {0}")]
     Text(#[source] reqwest::Error),
 
+    #[error("failed to deserialize row from JSON lines response: {0}")]
+    Row(#[source] serde_json::Error),
+
     #[error("server responded with error [{code}]: {message}")]
     ApiError { code: StatusCode, message: String },
+
+    #[error("request timed out after {0:?}")]
+    Timeout(Duration),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+impl Error {
+    /// Whether the request that produced this error was not processed by the server, and so
+    /// may be retried without risk of it being applied twice
+    ///
+    /// Timeouts and most 5xx responses are not retryable, since the server may have
+    /// applied the request before failing to respond.
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::WriteLpSend(e) => e.is_connect(),
+            Self::ApiError { code, .. } => matches!(
+                *code,
+                StatusCode::SERVICE_UNAVAILABLE | StatusCode::TOO_MANY_REQUESTS
+            ),
+            _ => false,
+        }
+    }
+
+    /// Convert an error from the HTTP client with `f`, unless the request timed out, in
+    /// which case it is reported as [`Error::Timeout`]
+    fn from_request(
+        e: reqwest::Error,
+        timeout: Duration,
+        f: impl FnOnce(reqwest::Error) -> Self,
+    ) -> Self {
+        if e.is_timeout() {
+            Self::Timeout(timeout)
+        } else {
+            f(e)
+        }
+    }
+}
+
+/// The default time allowed for a request to complete, see [`Client::with_timeout`]
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// The InfluxDB 3.0 Client
 ///
 /// For programmatic access to the HTTP API of InfluxDB 3.0
@@ -66,6 +137,12 @@ pub struct Client {
     base_url: Url,
     /// The `Bearer` token to use for authenticating on each request to the server
     auth_token: Option<Secret<String>>,
+    /// Additional root certificates trusted when connecting to the server over TLS
+    root_certs: Vec<reqwest::Certificate>,
+    /// Whether to skip verification of the server's TLS certificate
+    accept_invalid_certs: bool,
+    /// The time allowed for each request to complete
+    timeout: Duration,
     /// A [`reqwest::Client`] for handling HTTP requests
     http_client: reqwest::Client,
 }
@@ -73,11 +150,16 @@ pub struct Client {
 impl Client {
     /// Create a new [`Client`]
     pub fn new<U: IntoUrl>(base_url: U) -> Result<Self> {
-        Ok(Self {
+        let mut client = Self {
             base_url: base_url.into_url().map_err(Error::BaseUrl)?,
             auth_token: None,
+            root_certs: vec![],
+            accept_invalid_certs: false,
+            timeout: DEFAULT_TIMEOUT,
             http_client: reqwest::Client::new(),
-        })
+        };
+        client.http_client = client.build_http_client()?;
+        Ok(client)
     }
 
     /// Set the `Bearer` token that will be sent with each request to the server
@@ -99,6 +181,83 @@ impl Client {
         self
     }
 
+    /// Trust the given PEM-encoded root certificate when connecting to the server over TLS
+    ///
+    /// This is needed to connect to a server whose certificate is signed by a private CA,
+    /// or is self-signed. It may be called more than once to trust several certificates.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let pem = std::fs::read("ca.pem")?;
+    /// let client = Client::new("https://localhost:8181")?
+    ///     .with_root_cert(pem)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_root_cert<P: AsRef<[u8]>>(mut self, pem: P) -> Result<Self> {
+        let cert = reqwest::Certificate::from_pem(pem.as_ref()).map_err(Error::RootCert)?;
+        self.root_certs.push(cert);
+        self.http_client = self.build_http_client().map_err(|e| match e {
+            Error::HttpClient(e) => Error::RootCert(e),
+            e => e,
+        })?;
+        Ok(self)
+    }
+
+    /// Skip verification of the server's TLS certificate
+    ///
+    /// # Warning
+    ///
+    /// This accepts any certificate the server presents, including expired certificates and
+    /// certificates for other hosts, and so should only be used in test environments.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Result<Self> {
+        self.accept_invalid_certs = accept;
+        self.http_client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    /// Set the time allowed for each request to complete, after which it fails with
+    /// [`Error::Timeout`]
+    ///
+    /// This defaults to [`DEFAULT_TIMEOUT`]. The timeout covers the entire request,
+    /// including reading the response, except for [`QueryRequestBuilder::stream_rows`],
+    /// which may stream rows for as long as the server keeps sending them: there, the
+    /// timeout bounds connecting and receiving the response headers, and how long to wait
+    /// for each subsequent chunk of the response.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::time::Duration;
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?
+    ///     .with_timeout(Duration::from_secs(5))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.timeout = timeout;
+        self.http_client = self.build_http_client()?;
+        Ok(self)
+    }
+
+    fn build_http_client(&self) -> Result<reqwest::Client> {
+        self.root_certs
+            .iter()
+            .cloned()
+            .fold(reqwest::Client::builder(), |builder, cert| {
+                builder.add_root_certificate(cert)
+            })
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .connect_timeout(self.timeout)
+            .build()
+            .map_err(Error::HttpClient)
+    }
+
     /// Compose a request to the `/api/v3/write_lp` API
     ///
     /// # Example
@@ -125,10 +284,45 @@ impl Client {
             db: db.into(),
             precision: None,
             accept_partial: None,
+            gzip: false,
+            max_attempts: 1,
             body: NoBody,
         }
     }
 
+    /// Create a [`WriteBatcher`] that buffers line protocol written to the `db` database
+    /// and sends it to the `/api/v3/write_lp` API in batches
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # use influxdb3_client::Precision;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let mut batcher = client
+    ///     .write_batcher("db_name")
+    ///     .precision(Precision::Second)
+    ///     .max_lines(1_000);
+    /// for i in 0..10_000 {
+    ///     batcher.write(format!("cpu,host=s1 usage={i} {i}")).await?;
+    /// }
+    /// batcher.flush().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn write_batcher<S: Into<String>>(&self, db: S) -> WriteBatcher {
+        WriteBatcher {
+            client: self.clone(),
+            db: db.into(),
+            precision: None,
+            max_lines: DEFAULT_BATCH_MAX_LINES,
+            flush_interval: DEFAULT_BATCH_FLUSH_INTERVAL,
+            state: Default::default(),
+            flusher_started: false,
+        }
+    }
+
     /// Compose a request to the `/api/v3/query_sql` API
     ///
     /// # Example
@@ -191,17 +385,53 @@ impl Client {
         }
     }
 
+    /// Run a SQL query with `/api/v3/query_sql`, producing the result as CSV text
+    ///
+    /// The first line of the output is a header of the result's column names, as
+    /// serialized by the server.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let csv = client
+    ///     .query_csv("db_name", "SELECT * FROM foo")
+    ///     .await
+    ///     .expect("send query_sql request");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_csv<D: Into<String>, Q: Into<String>>(
+        &self,
+        db: D,
+        query: Q,
+    ) -> Result<String> {
+        let bytes = self
+            .api_v3_query_sql(db, query)
+            .format(Format::Csv)
+            .send()
+            .await?;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+
     /// Send a `/ping` request to the target `influxdb3` server to check its
     /// status and gather `version` and `revision` information
     pub async fn ping(&self) -> Result<PingResponse> {
         let url = self.base_url.join("/ping")?;
-        let mut req = self.http_client.get(url);
+        let mut req = self.http_client.get(url).timeout(self.timeout);
         if let Some(t) = &self.auth_token {
             req = req.bearer_auth(t.expose_secret());
         }
-        let resp = req.send().await.map_err(Error::PingSend)?;
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| Error::from_request(e, self.timeout, Error::PingSend))?;
         if resp.status().is_success() {
-            resp.json().await.map_err(Error::Json)
+            resp.json()
+                .await
+                .map_err(|e| Error::from_request(e, self.timeout, Error::Json))
         } else {
             Err(Error::ApiError {
                 code: resp.status(),
@@ -209,6 +439,156 @@ impl Client {
             })
         }
     }
+
+    /// Send a `/health` request to the target `influxdb3` server to check whether it is
+    /// ready to serve requests
+    ///
+    /// A server that is up but reporting backpressure responds successfully, with a
+    /// status that is not [`HealthResponse::is_ok`].
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let health = client.health().await?;
+    /// if !health.is_ok() {
+    ///     println!("server is unhealthy: {:?}", health.message());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn health(&self) -> Result<HealthResponse> {
+        let url = self.base_url.join("/health")?;
+        let mut req = self
+            .http_client
+            .get(url)
+            .query(&[("verbose", true)])
+            .timeout(self.timeout);
+        if let Some(t) = &self.auth_token {
+            req = req.bearer_auth(t.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| Error::from_request(e, self.timeout, Error::HealthSend))?;
+        match resp.status() {
+            StatusCode::OK | StatusCode::SERVICE_UNAVAILABLE => resp
+                .json()
+                .await
+                .map_err(|e| Error::from_request(e, self.timeout, Error::Json)),
+            code => Err(Error::ApiError {
+                code,
+                message: resp.text().await.map_err(Error::Text)?,
+            }),
+        }
+    }
+
+    /// Send a `/api/v3/capabilities` request to the target `influxdb3` server to discover
+    /// the write and query formats and APIs that it supports
+    pub async fn api_v3_capabilities(&self) -> Result<Capabilities> {
+        let url = self.base_url.join("/api/v3/capabilities")?;
+        let mut req = self.http_client.get(url).timeout(self.timeout);
+        if let Some(t) = &self.auth_token {
+            req = req.bearer_auth(t.expose_secret());
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| Error::from_request(e, self.timeout, Error::CapabilitiesSend))?;
+        if resp.status().is_success() {
+            resp.json()
+                .await
+                .map_err(|e| Error::from_request(e, self.timeout, Error::Json))
+        } else {
+            Err(Error::ApiError {
+                code: resp.status(),
+                message: resp.text().await.map_err(Error::Text)?,
+            })
+        }
+    }
+}
+
+/// The response of the `/api/v3/capabilities` API on `influxdb3`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Capabilities {
+    version: String,
+    revision: String,
+    write: WriteCapabilities,
+    query: QueryCapabilities,
+}
+
+impl Capabilities {
+    /// Get the `version` of the server
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Get the `revision` of the server
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+
+    /// Get the capabilities of the server's write APIs
+    pub fn write(&self) -> &WriteCapabilities {
+        &self.write
+    }
+
+    /// Get the capabilities of the server's query APIs
+    pub fn query(&self) -> &QueryCapabilities {
+        &self.query
+    }
+}
+
+/// The write capabilities of an `influxdb3` server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WriteCapabilities {
+    formats: Vec<String>,
+    precisions: Vec<String>,
+    endpoints: Vec<String>,
+}
+
+impl WriteCapabilities {
+    /// Get the supported write formats, e.g., `line_protocol`
+    pub fn formats(&self) -> &[String] {
+        &self.formats
+    }
+
+    /// Get the supported timestamp precisions
+    pub fn precisions(&self) -> &[String] {
+        &self.precisions
+    }
+
+    /// Get the paths of the write APIs
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
+}
+
+/// The query capabilities of an `influxdb3` server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QueryCapabilities {
+    languages: Vec<String>,
+    formats: Vec<String>,
+    endpoints: Vec<String>,
+}
+
+impl QueryCapabilities {
+    /// Get the supported query languages, e.g., `sql`
+    pub fn languages(&self) -> &[String] {
+        &self.languages
+    }
+
+    /// Get the supported query output formats
+    pub fn formats(&self) -> &[String] {
+        &self.formats
+    }
+
+    /// Get the paths of the query APIs
+    pub fn endpoints(&self) -> &[String] {
+        &self.endpoints
+    }
 }
 
 /// The response of the `/ping` API on `influxdb3`
@@ -216,6 +596,12 @@ impl Client {
 pub struct PingResponse {
     version: String,
     revision: String,
+    #[serde(default)]
+    git_commit: Option<String>,
+    #[serde(default)]
+    build_date: Option<String>,
+    #[serde(default)]
+    api_versions: Vec<String>,
 }
 
 impl PingResponse {
@@ -228,6 +614,180 @@ impl PingResponse {
     pub fn revision(&self) -> &str {
         &self.revision
     }
+
+    /// Get the full `git_commit` hash from the response, if the server reported it
+    pub fn git_commit(&self) -> Option<&str> {
+        self.git_commit.as_deref()
+    }
+
+    /// Get the `build_date` from the response, if the server reported it
+    pub fn build_date(&self) -> Option<&str> {
+        self.build_date.as_deref()
+    }
+
+    /// Get the API versions that the server supports
+    pub fn api_versions(&self) -> &[String] {
+        &self.api_versions
+    }
+}
+
+/// The response of the `/health` API on `influxdb3`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+    version: String,
+    revision: String,
+    uptime_seconds: u64,
+    catalog: CatalogInfo,
+    checks: HealthChecks,
+}
+
+impl HealthResponse {
+    /// Get the overall `status` of the server, e.g., `ok`, `backpressure`, or `error`
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Whether the server reported itself as healthy
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+
+    /// Get the reason the server is unhealthy, if it is
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// Get the `version` of the server
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Get the `revision` of the server
+    pub fn revision(&self) -> &str {
+        &self.revision
+    }
+
+    /// Get how long, in seconds, the server has been running
+    pub fn uptime_seconds(&self) -> u64 {
+        self.uptime_seconds
+    }
+
+    /// Get information about the server's catalog
+    pub fn catalog(&self) -> &CatalogInfo {
+        &self.catalog
+    }
+
+    /// Get the health of the server's individual components
+    pub fn checks(&self) -> &HealthChecks {
+        &self.checks
+    }
+}
+
+/// The health of the components of an `influxdb3` server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthChecks {
+    wal: WalHealth,
+    write_buffer: WriteBufferHealth,
+    object_store: ObjectStoreHealth,
+}
+
+impl HealthChecks {
+    /// Get the health of the write-ahead log
+    pub fn wal(&self) -> &WalHealth {
+        &self.wal
+    }
+
+    /// Get the health of the write buffer
+    pub fn write_buffer(&self) -> &WriteBufferHealth {
+        &self.write_buffer
+    }
+
+    /// Get the health of the object store
+    pub fn object_store(&self) -> &ObjectStoreHealth {
+        &self.object_store
+    }
+}
+
+/// Information about the catalog of an `influxdb3` server
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct CatalogInfo {
+    databases: usize,
+}
+
+impl CatalogInfo {
+    /// Get the number of databases in the catalog
+    pub fn databases(&self) -> usize {
+        self.databases
+    }
+}
+
+/// The health of the write-ahead log of an `influxdb3` server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WalHealth {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+impl WalHealth {
+    /// Get the `status` of the write-ahead log, which is `disabled` if the server runs
+    /// without one, or `error` if its directory could not be read
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Get the reason the write-ahead log is unhealthy, if it is
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+/// The health of the write buffer of an `influxdb3` server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WriteBufferHealth {
+    status: String,
+    size_bytes: usize,
+    limit_bytes: usize,
+}
+
+impl WriteBufferHealth {
+    /// Get the `status` of the write buffer
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Get the number of bytes currently held in the write buffer
+    pub fn size_bytes(&self) -> usize {
+        self.size_bytes
+    }
+
+    /// Get the number of bytes the write buffer is limited to
+    pub fn limit_bytes(&self) -> usize {
+        self.limit_bytes
+    }
+}
+
+/// The health of the object store of an `influxdb3` server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ObjectStoreHealth {
+    status: String,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+impl ObjectStoreHealth {
+    /// Get the `status` of the object store, which is `error` if it could not be listed
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Get the reason the object store is unreachable, if it is
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
 }
 
 /// The URL parameters of the request to the `/api/v3/write_lp` API
@@ -240,16 +800,6 @@ struct WriteParams<'a> {
     accept_partial: Option<bool>,
 }
 
-impl<'a, B> From<&'a WriteRequestBuilder<'a, B>> for WriteParams<'a> {
-    fn from(builder: &'a WriteRequestBuilder<'a, B>) -> Self {
-        Self {
-            db: &builder.db,
-            precision: builder.precision,
-            accept_partial: builder.accept_partial,
-        }
-    }
-}
-
 /// Time series precision
 // TODO - this should re-use a type defined in the server code, or a separate crate,
 //        central to both.
@@ -271,6 +821,8 @@ pub struct WriteRequestBuilder<'c, B> {
     db: String,
     precision: Option<Precision>,
     accept_partial: Option<bool>,
+    gzip: bool,
+    max_attempts: usize,
     body: B,
 }
 
@@ -281,56 +833,365 @@ impl<'c, B> WriteRequestBuilder<'c, B> {
         self
     }
 
-    /// Set the `accept_partial` parameter
-    pub fn accept_partial(mut self, set_to: bool) -> Self {
-        self.accept_partial = Some(set_to);
+    /// Set the `accept_partial` parameter
+    pub fn accept_partial(mut self, set_to: bool) -> Self {
+        self.accept_partial = Some(set_to);
+        self
+    }
+
+    /// Gzip-compress the body of the request
+    pub fn gzip(mut self, set_to: bool) -> Self {
+        self.gzip = set_to;
+        self
+    }
+
+    /// Make up to `max_attempts` attempts at sending the request, retrying with exponential
+    /// backoff when the server cannot be connected to, or responds with a `503 Service
+    /// Unavailable` or `429 Too Many Requests` error
+    ///
+    /// Only failures where the server did not process the write are retried, so timeouts
+    /// and other errors are returned without retrying. Writes are not idempotent in
+    /// general: lines without a timestamp are given the time at which the server ingests
+    /// them, so a write that is applied more than once duplicates those lines. Defaults to
+    /// a single attempt.
+    pub fn max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+}
+
+impl<'c> WriteRequestBuilder<'c, NoBody> {
+    /// Set the body of the request to the `/api/v3/write_lp` API
+    ///
+    /// This essentially wraps `reqwest`'s [`body`][reqwest::RequestBuilder::body]
+    /// method, and puts the responsibility on the caller for now.
+    pub fn body<T: Into<Body>>(self, body: T) -> WriteRequestBuilder<'c, Body> {
+        WriteRequestBuilder {
+            client: self.client,
+            db: self.db,
+            precision: self.precision,
+            accept_partial: self.accept_partial,
+            gzip: self.gzip,
+            max_attempts: self.max_attempts,
+            body: body.into(),
+        }
+    }
+}
+
+/// The delay before the first retry of a write, which doubles on each subsequent retry
+const WRITE_RETRY_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// The longest delay between retries of a write
+const WRITE_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+impl<'c> WriteRequestBuilder<'c, Body> {
+    /// Send the request to the server
+    pub async fn send(self) -> Result<()> {
+        let Self {
+            client,
+            db,
+            precision,
+            accept_partial,
+            gzip,
+            max_attempts,
+            body,
+        } = self;
+        let url = client.base_url.join("/api/v3/write_lp")?;
+        let params = WriteParams {
+            db: &db,
+            precision,
+            accept_partial,
+        };
+        if !gzip && max_attempts == 1 {
+            return send_write(client, &url, &params, false, body).await;
+        }
+
+        let body = body.as_bytes().ok_or(Error::WriteLpStreamingBody)?;
+        let body = if gzip {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(body).map_err(Error::WriteLpGzip)?;
+            Bytes::from(encoder.finish().map_err(Error::WriteLpGzip)?)
+        } else {
+            Bytes::copy_from_slice(body)
+        };
+
+        let mut backoff = WRITE_RETRY_INITIAL_BACKOFF;
+        let mut attempt = 1;
+        loop {
+            match send_write(client, &url, &params, gzip, body.clone().into()).await {
+                Err(e) if attempt < max_attempts && e.is_retryable() => {
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(WRITE_RETRY_MAX_BACKOFF);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+async fn send_write(
+    client: &Client,
+    url: &Url,
+    params: &WriteParams<'_>,
+    gzip: bool,
+    body: Body,
+) -> Result<()> {
+    let mut req = client
+        .http_client
+        .post(url.clone())
+        .query(params)
+        .timeout(client.timeout);
+    if let Some(token) = &client.auth_token {
+        req = req.bearer_auth(token.expose_secret());
+    }
+    if gzip {
+        req = req.header(CONTENT_ENCODING, "gzip");
+    }
+    let resp = req
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| Error::from_request(e, client.timeout, Error::WriteLpSend))?;
+    let status = resp.status();
+    let content = resp
+        .bytes()
+        .await
+        .map_err(|e| Error::from_request(e, client.timeout, Error::Bytes))?;
+    match status {
+        // TODO - handle the OK response content, return to caller, etc.
+        StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
+        code => Err(Error::ApiError {
+            code,
+            message: String::from_utf8(content.to_vec())?,
+        }),
+    }
+}
+
+/// The default number of lines that a [`WriteBatcher`] buffers before sending them
+pub const DEFAULT_BATCH_MAX_LINES: usize = 5_000;
+/// The default age of the oldest buffered line at which a [`WriteBatcher`] sends its batch
+pub const DEFAULT_BATCH_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Buffers line protocol and writes it to `/api/v3/write_lp` in batches
+///
+/// A batch is sent once it holds [`max_lines`][WriteBatcher::max_lines] lines, or by a
+/// background task once the oldest buffered line has waited for the
+/// [`flush_interval`][WriteBatcher::flush_interval]. Errors from batches sent in the
+/// background are returned by the next call to [`flush`][WriteBatcher::flush].
+///
+/// Calling [`flush`][WriteBatcher::flush] is the only way to guarantee that all lines are
+/// sent, and to find out whether they were written. If the batcher is dropped with lines
+/// still buffered, the background task makes a final attempt to send them, but it does not
+/// outlive the tokio runtime, and any error is discarded.
+///
+/// All batches are sent using the HTTP client, and so the connection pool, of the
+/// [`Client`] that produced the batcher.
+///
+/// Produced by [`Client::write_batcher`]
+#[derive(Debug)]
+pub struct WriteBatcher {
+    client: Client,
+    db: String,
+    precision: Option<Precision>,
+    max_lines: usize,
+    flush_interval: Duration,
+    state: Arc<BatchState>,
+    flusher_started: bool,
+}
+
+/// The state of a [`WriteBatcher`] that is shared with its background flush task
+#[derive(Debug, Default)]
+struct BatchState {
+    batch: Mutex<Batch>,
+    /// Notifies the background flush task that lines were buffered, or the batcher was
+    /// dropped
+    wake: Notify,
+    /// Held while a batch is taken from the buffer and sent, so that batches are sent one
+    /// at a time, in the order they were buffered
+    sending: AsyncMutex<()>,
+}
+
+#[derive(Debug, Default)]
+struct Batch {
+    buffer: String,
+    lines: usize,
+    first_buffered_at: Option<tokio::time::Instant>,
+    /// The first error from a batch sent by the background flush task
+    error: Option<Error>,
+    /// Whether the [`WriteBatcher`] has been dropped
+    closed: bool,
+}
+
+impl BatchState {
+    fn lock(&self) -> MutexGuard<'_, Batch> {
+        self.batch.lock().expect("write batch mutex poisoned")
+    }
+
+    /// Take the buffered lines, if there are any
+    fn take_lines(&self) -> Option<String> {
+        let mut batch = self.lock();
+        if batch.lines == 0 {
+            return None;
+        }
+        batch.lines = 0;
+        batch.first_buffered_at = None;
+        Some(std::mem::take(&mut batch.buffer))
+    }
+
+    /// Send the buffered lines, if there are any, once any batch already being sent has
+    /// been sent
+    async fn send_lines(
+        &self,
+        client: &Client,
+        db: &str,
+        precision: Option<Precision>,
+    ) -> Result<()> {
+        let _sending = self.sending.lock().await;
+        match self.take_lines() {
+            Some(body) => send_batch(client, db, precision, body).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl WriteBatcher {
+    /// Set the precision of the timestamps in the written lines
+    pub fn precision(mut self, set_to: Precision) -> Self {
+        self.precision = Some(set_to);
+        self
+    }
+
+    /// Set the number of lines to buffer before sending them as a batch
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = max_lines.max(1);
+        self
+    }
+
+    /// Set how long the oldest buffered line may wait before the batch is sent
+    pub fn flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
         self
     }
-}
 
-impl<'c> WriteRequestBuilder<'c, NoBody> {
-    /// Set the body of the request to the `/api/v3/write_lp` API
+    /// Get the number of lines that are buffered and not yet sent
+    pub fn buffered_lines(&self) -> usize {
+        self.state.lock().lines
+    }
+
+    /// Buffer a line of line protocol, sending the batch if it is full
     ///
-    /// This essentially wraps `reqwest`'s [`body`][reqwest::RequestBuilder::body]
-    /// method, and puts the responsibility on the caller for now.
-    pub fn body<T: Into<Body>>(self, body: T) -> WriteRequestBuilder<'c, Body> {
-        WriteRequestBuilder {
-            client: self.client,
-            db: self.db,
-            precision: self.precision,
-            accept_partial: self.accept_partial,
-            body: body.into(),
+    /// The first call starts the background task that sends batches once they have waited
+    /// for the flush interval, so this must be called from within a tokio runtime.
+    pub async fn write<L: AsRef<str>>(&mut self, line: L) -> Result<()> {
+        let line = line.as_ref().trim_end_matches('\n');
+        if line.is_empty() {
+            return Ok(());
+        }
+        if !self.flusher_started {
+            tokio::spawn(flush_batches(
+                Arc::clone(&self.state),
+                self.client.clone(),
+                self.db.clone(),
+                self.precision,
+                self.flush_interval,
+            ));
+            self.flusher_started = true;
+        }
+
+        let lines = {
+            let mut batch = self.state.lock();
+            batch.buffer.push_str(line);
+            batch.buffer.push('\n');
+            batch.lines += 1;
+            batch
+                .first_buffered_at
+                .get_or_insert_with(tokio::time::Instant::now);
+            batch.lines
+        };
+        if lines == 1 {
+            self.state.wake.notify_one();
+        }
+        if lines >= self.max_lines {
+            self.state
+                .send_lines(&self.client, &self.db, self.precision)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Send all buffered lines to the server
+    ///
+    /// This waits for any batch that the background task is sending to be sent first. The
+    /// buffer is cleared whether or not the write succeeds. If the write succeeds, but a
+    /// batch sent in the background since the last call failed, that error is returned.
+    pub async fn flush(&mut self) -> Result<()> {
+        self.state
+            .send_lines(&self.client, &self.db, self.precision)
+            .await?;
+        match self.state.lock().error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
         }
     }
 }
 
-impl<'c> WriteRequestBuilder<'c, Body> {
-    /// Send the request to the server
-    pub async fn send(self) -> Result<()> {
-        let url = self.client.base_url.join("/api/v3/write_lp")?;
-        let params = WriteParams::from(&self);
-        let mut req = self.client.http_client.post(url).query(&params);
-        if let Some(token) = &self.client.auth_token {
-            req = req.bearer_auth(token.expose_secret());
+impl Drop for WriteBatcher {
+    fn drop(&mut self) {
+        self.state.lock().closed = true;
+        self.state.wake.notify_one();
+    }
+}
+
+/// Send the batches of a [`WriteBatcher`] once the oldest line in them has waited for
+/// `flush_interval`, until the batcher is dropped
+async fn flush_batches(
+    state: Arc<BatchState>,
+    client: Client,
+    db: String,
+    precision: Option<Precision>,
+    flush_interval: Duration,
+) {
+    loop {
+        let (closed, deadline) = {
+            let batch = state.lock();
+            (
+                batch.closed,
+                batch.first_buffered_at.map(|t| t + flush_interval),
+            )
+        };
+        if closed || deadline.is_some_and(|d| d <= tokio::time::Instant::now()) {
+            if let Err(e) = state.send_lines(&client, &db, precision).await {
+                state.lock().error.get_or_insert(e);
+            }
+            if closed {
+                return;
+            }
+            continue;
         }
-        let resp = req
-            .body(self.body)
-            .send()
-            .await
-            .map_err(Error::WriteLpSend)?;
-        let status = resp.status();
-        let content = resp.bytes().await.map_err(Error::Bytes)?;
-        match status {
-            // TODO - handle the OK response content, return to caller, etc.
-            StatusCode::OK => Ok(()),
-            code => Err(Error::ApiError {
-                code,
-                message: String::from_utf8(content.to_vec())?,
-            }),
+        match deadline {
+            Some(deadline) => tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => {}
+                _ = state.wake.notified() => {}
+            },
+            None => state.wake.notified().await,
         }
     }
 }
 
+async fn send_batch(
+    client: &Client,
+    db: &str,
+    precision: Option<Precision>,
+    body: String,
+) -> Result<()> {
+    let mut req = client.api_v3_write_lp(db);
+    if let Some(precision) = precision {
+        req = req.precision(precision);
+    }
+    req.body(body).send().await
+}
+
 #[doc(hidden)]
 /// Typestate type for [`WriteRequestBuilder`]
 #[derive(Debug, Copy, Clone)]
@@ -470,28 +1331,119 @@ impl<'c> QueryRequestBuilder<'c> {
 
     /// Send the request to `/api/v3/query_sql` or `/api/v3/query_influxql`
     pub async fn send(self) -> Result<Bytes> {
+        let resp = self.send_request(Some(self.client.timeout)).await?;
+        resp.bytes()
+            .await
+            .map_err(|e| Error::from_request(e, self.client.timeout, Error::Bytes))
+    }
+
+    /// Send the request and stream back the result rows, deserialized into `T`
+    ///
+    /// This requests the `jsonl` format from the server, overriding any format
+    /// set on the builder, and deserializes each line of the response as it
+    /// arrives, so that large results can be processed without buffering the
+    /// entire response in memory.
+    ///
+    /// The stream is not bounded by the client's [timeout][Client::with_timeout] as a
+    /// whole; instead, the stream fails with [`Error::Timeout`] if the server takes longer
+    /// than the timeout to respond, or to send each chunk of the response.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use influxdb3_client::Client;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    /// use futures::TryStreamExt;
+    /// use serde_json::{Map, Value};
+    ///
+    /// let client = Client::new("http://localhost:8181")?;
+    /// let mut rows = client
+    ///     .api_v3_query_sql("db_name", "SELECT * FROM foo")
+    ///     .stream_rows::<Map<String, Value>>()
+    ///     .await?;
+    /// while let Some(row) = rows.try_next().await? {
+    ///     println!("{row:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn stream_rows<T: DeserializeOwned>(
+        mut self,
+    ) -> Result<impl Stream<Item = Result<T>> + Unpin> {
+        self.format = Some(Format::JsonLines);
+        let timeout = self.client.timeout;
+        let resp = tokio::time::timeout(timeout, self.send_request(None))
+            .await
+            .map_err(|_| Error::Timeout(timeout))??;
+        let bytes = Box::pin(resp.bytes_stream());
+
+        Ok(Box::pin(futures::stream::try_unfold(
+            (bytes, Vec::new()),
+            move |(mut bytes, mut buf)| async move {
+                loop {
+                    // Yield a row for each complete line in the buffer:
+                    if let Some(pos) = buf.iter().position(|b| *b == b'\n') {
+                        let line = buf.drain(..=pos).collect::<Vec<u8>>();
+                        if line.iter().all(u8::is_ascii_whitespace) {
+                            continue;
+                        }
+                        let row = serde_json::from_slice(&line).map_err(Error::Row)?;
+                        return Ok(Some((row, (bytes, buf))));
+                    }
+                    let next = tokio::time::timeout(timeout, bytes.next())
+                        .await
+                        .map_err(|_| Error::Timeout(timeout))?;
+                    match next {
+                        Some(chunk) => buf.extend_from_slice(
+                            &chunk.map_err(|e| Error::from_request(e, timeout, Error::Bytes))?,
+                        ),
+                        // The final line may not be terminated with a newline:
+                        None if !buf.iter().all(u8::is_ascii_whitespace) => {
+                            let row = serde_json::from_slice(&buf).map_err(Error::Row)?;
+                            buf.clear();
+                            return Ok(Some((row, (bytes, buf))));
+                        }
+                        None => return Ok(None),
+                    }
+                }
+            },
+        )))
+    }
+
+    /// Send the request, allowing `request_timeout` for the entire request to complete, if set
+    async fn send_request(&self, request_timeout: Option<Duration>) -> Result<reqwest::Response> {
         let url = match self.kind {
             QueryKind::Sql => self.client.base_url.join("/api/v3/query_sql")?,
             QueryKind::InfluxQl => self.client.base_url.join("/api/v3/query_influxql")?,
         };
-        let params = QueryParams::from(&self);
+        let params = QueryParams::from(self);
         let mut req = self.client.http_client.post(url).json(&params);
+        if let Some(request_timeout) = request_timeout {
+            req = req.timeout(request_timeout);
+        }
         if let Some(token) = &self.client.auth_token {
             req = req.bearer_auth(token.expose_secret());
         }
-        let resp = req.send().await.map_err(|source| Error::QuerySend {
-            kind: self.kind,
-            source,
+        let timeout = self.client.timeout;
+        let resp = req.send().await.map_err(|e| {
+            Error::from_request(e, timeout, |source| Error::QuerySend {
+                kind: self.kind,
+                source,
+            })
         })?;
-        let status = resp.status();
-        let content = resp.bytes().await.map_err(Error::Bytes)?;
 
-        match status {
-            StatusCode::OK => Ok(content),
-            code => Err(Error::ApiError {
-                code,
-                message: String::from_utf8(content.to_vec()).map_err(Error::InvalidUtf8)?,
-            }),
+        match resp.status() {
+            StatusCode::OK => Ok(resp),
+            code => {
+                let content = resp
+                    .bytes()
+                    .await
+                    .map_err(|e| Error::from_request(e, timeout, Error::Bytes))?;
+                Err(Error::ApiError {
+                    code,
+                    message: String::from_utf8(content.to_vec()).map_err(Error::InvalidUtf8)?,
+                })
+            }
         }
     }
 }
@@ -538,17 +1490,33 @@ impl Display for QueryKind {
 #[serde(rename_all = "snake_case")]
 pub enum Format {
     Json,
+    #[serde(rename = "jsonl")]
+    JsonLines,
     Csv,
     Parquet,
     Pretty,
+    /// The Arrow IPC streaming format
+    Arrow,
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        io::{Read, Write},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+    use futures::TryStreamExt;
     use mockito::{Matcher, Server};
+    use reqwest::StatusCode;
+    use serde::Deserialize;
     use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::{rustls, TlsAcceptor};
 
-    use crate::{Client, Format, Precision};
+    use crate::{Client, Error, Format, Precision};
 
     #[tokio::test]
     async fn api_v3_write_lp() {
@@ -587,6 +1555,347 @@ mod tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn api_v3_write_lp_gzip_with_retries() {
+        let db = "stats";
+        let body = "cpu,host=s1 usage=0.5\ncpu,host=s2 usage=0.7";
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(body.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let mut decoded = String::new();
+        GzDecoder::new(gzipped.as_slice())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+
+        let mut mock_server = Server::new_async().await;
+        // The server is unavailable for the first two attempts:
+        let unavailable = mock_server
+            .mock("POST", "/api/v3/write_lp")
+            .match_query(Matcher::UrlEncoded("db".into(), db.into()))
+            .match_header("Content-Encoding", "gzip")
+            .match_body(gzipped.clone())
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        let available = mock_server
+            .mock("POST", "/api/v3/write_lp")
+            .match_query(Matcher::UrlEncoded("db".into(), db.into()))
+            .match_header("Content-Encoding", "gzip")
+            .match_body(gzipped)
+            .with_status(204)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        client
+            .api_v3_write_lp(db)
+            .gzip(true)
+            .max_attempts(3)
+            .body(body)
+            .send()
+            .await
+            .expect("send write_lp request");
+
+        unavailable.assert_async().await;
+        available.assert_async().await;
+
+        // The final error is returned once all attempts have failed:
+        let mut mock_server = Server::new_async().await;
+        let unavailable = mock_server
+            .mock("POST", "/api/v3/write_lp")
+            .match_query(Matcher::UrlEncoded("db".into(), db.into()))
+            .with_status(503)
+            .with_body("try again later")
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        let err = client
+            .api_v3_write_lp(db)
+            .max_attempts(2)
+            .body(body)
+            .send()
+            .await
+            .expect_err("write_lp request should fail");
+        assert!(
+            matches!(
+                &err,
+                Error::ApiError { code, message }
+                    if *code == StatusCode::SERVICE_UNAVAILABLE && message == "try again later"
+            ),
+            "unexpected error: {err}"
+        );
+
+        unavailable.assert_async().await;
+
+        // Errors after which the write may have been applied are not retried:
+        let mut mock_server = Server::new_async().await;
+        let internal_error = mock_server
+            .mock("POST", "/api/v3/write_lp")
+            .match_query(Matcher::UrlEncoded("db".into(), db.into()))
+            .with_status(500)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        let err = client
+            .api_v3_write_lp(db)
+            .max_attempts(3)
+            .body(body)
+            .send()
+            .await
+            .expect_err("write_lp request should fail");
+        assert!(
+            matches!(&err, Error::ApiError { code, .. } if *code == StatusCode::INTERNAL_SERVER_ERROR),
+            "unexpected error: {err}"
+        );
+
+        internal_error.assert_async().await;
+    }
+
+    #[test]
+    fn with_root_cert_rejects_invalid_certificate() {
+        let pem = "\
+            -----BEGIN CERTIFICATE-----\n\
+            bm90IGEgY2VydGlmaWNhdGU=\n\
+            -----END CERTIFICATE-----\n";
+        let result = Client::new("https://localhost:8181")
+            .expect("create client")
+            .with_root_cert(pem);
+        assert!(
+            matches!(result, Err(Error::RootCert(_))),
+            "unexpected result: {result:?}"
+        );
+    }
+
+    #[test]
+    fn danger_accept_invalid_certs() {
+        Client::new("https://localhost:8181")
+            .expect("create client")
+            .danger_accept_invalid_certs(true)
+            .expect("build client that skips certificate verification");
+    }
+
+    #[tokio::test]
+    async fn write_batcher_background_error() {
+        let mut mock_server = Server::new_async().await;
+        let internal_error = mock_server
+            .mock("POST", "/api/v3/write_lp")
+            .match_query(Matcher::UrlEncoded("db".into(), "stats".into()))
+            .with_status(500)
+            .with_body("internal error")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+        let mut batcher = client
+            .write_batcher("stats")
+            .flush_interval(Duration::from_millis(10));
+        batcher
+            .write("cpu,host=s1 usage=0.5")
+            .await
+            .expect("buffer line");
+
+        // Wait for the background task to take the batch, so that it, and not the call to
+        // flush, is what sends it:
+        while batcher.buffered_lines() > 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        let err = batcher
+            .flush()
+            .await
+            .expect_err("flush should return the error from the background send");
+        assert!(
+            matches!(&err, Error::ApiError { code, .. } if *code == StatusCode::INTERNAL_SERVER_ERROR),
+            "unexpected error: {err}"
+        );
+        // The error is only returned once:
+        batcher.flush().await.expect("flush with nothing buffered");
+
+        internal_error.assert_async().await;
+    }
+
+    /// Spawn a server that answers every request to it with a `/ping` response, over TLS
+    /// with a self-signed certificate for `localhost`. Returns the server's URL and the
+    /// certificate, PEM-encoded.
+    async fn spawn_tls_server() -> (String, String) {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()])
+            .expect("generate self-signed certificate");
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![rustls::Certificate(
+                    cert.serialize_der().expect("serialize certificate"),
+                )],
+                rustls::PrivateKey(cert.serialize_private_key_der()),
+            )
+            .expect("create TLS server config");
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind listener");
+        let port = listener.local_addr().expect("get listener address").port();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    // Clients that do not trust the certificate fail the handshake:
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let body = r#"{"version":"3.0.0","revision":"abc123"}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\n\
+                        content-type: application/json\r\n\
+                        content-length: {}\r\n\
+                        connection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        (
+            format!("https://localhost:{port}"),
+            cert.serialize_pem().expect("serialize certificate"),
+        )
+    }
+
+    #[tokio::test]
+    async fn tls_self_signed_certificate() {
+        let (url, cert) = spawn_tls_server().await;
+
+        // The certificate is not signed by a trusted root, so it is rejected by default:
+        let err = Client::new(&url)
+            .expect("create client")
+            .ping()
+            .await
+            .expect_err("ping should fail certificate verification");
+        assert!(matches!(err, Error::PingSend(_)), "unexpected error: {err}");
+
+        let ping = Client::new(&url)
+            .expect("create client")
+            .with_root_cert(&cert)
+            .expect("add root certificate")
+            .ping()
+            .await
+            .expect("ping with the certificate as a trusted root");
+        assert_eq!(ping.version(), "3.0.0");
+
+        let ping = Client::new(&url)
+            .expect("create client")
+            .danger_accept_invalid_certs(true)
+            .expect("build client that skips certificate verification")
+            .ping()
+            .await
+            .expect("ping without certificate verification");
+        assert_eq!(ping.version(), "3.0.0");
+    }
+
+    #[tokio::test]
+    async fn request_timeout() {
+        // A listener that never accepts connections, so requests sent to it are never
+        // responded to:
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind listener");
+        let addr = listener.local_addr().expect("get listener address");
+        let timeout = Duration::from_millis(200);
+
+        let client = Client::new(format!("http://{addr}"))
+            .expect("create client")
+            .with_timeout(timeout)
+            .expect("set client timeout");
+
+        let start = Instant::now();
+        let result = client.ping().await;
+        let elapsed = start.elapsed();
+        assert!(
+            matches!(result, Err(Error::Timeout(t)) if t == timeout),
+            "unexpected result: {result:?}"
+        );
+        assert!(
+            elapsed < Duration::from_secs(5),
+            "request took {elapsed:?} to time out"
+        );
+
+        let result = client
+            .api_v3_query_sql("foo", "SELECT * FROM cpu")
+            .send()
+            .await;
+        assert!(
+            matches!(result, Err(Error::Timeout(_))),
+            "unexpected result: {result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn api_v3_capabilities() {
+        let body = json!({
+            "version": "3.0.0",
+            "revision": "abc123",
+            "write": {
+                "formats": ["line_protocol"],
+                "precisions": ["auto", "second", "millisecond", "microsecond", "nanosecond"],
+                "endpoints": ["/api/v3/write_lp"]
+            },
+            "query": {
+                "languages": ["sql", "influxql"],
+                "formats": ["json", "jsonl", "csv", "pretty", "parquet"],
+                "endpoints": ["/api/v3/query_sql", "/api/v3/query_influxql"]
+            }
+        });
+
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("GET", "/api/v3/capabilities")
+            .with_status(200)
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        let capabilities = client
+            .api_v3_capabilities()
+            .await
+            .expect("send request to server");
+
+        assert_eq!(capabilities.version(), "3.0.0");
+        assert_eq!(capabilities.write().formats(), ["line_protocol"]);
+        assert!(capabilities
+            .write()
+            .precisions()
+            .contains(&"nanosecond".to_string()));
+        assert_eq!(capabilities.query().languages(), ["sql", "influxql"]);
+        assert!(capabilities
+            .query()
+            .formats()
+            .contains(&"jsonl".to_string()));
+
+        mock.assert_async().await;
+    }
+
     #[tokio::test]
     async fn api_v3_query_sql() {
         let token = "super-secret-token";
@@ -628,6 +1937,137 @@ mod tests {
         mock.assert_async().await;
     }
 
+    #[tokio::test]
+    async fn api_v3_query_sql_stream_rows() {
+        let db = "stats";
+        let query = "SELECT * FROM foo";
+        let body = "{\"host\":\"a\",\"val\":1}\n\
+            {\"host\":\"b\",\"val\":2}\n\
+            {\"host\":\"c\",\"val\":3}\n";
+
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/query_sql")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": db,
+                "q": query,
+                "format": "jsonl",
+                "params": null,
+            })))
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Row {
+            host: String,
+            val: i64,
+        }
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        let rows = client
+            .api_v3_query_sql(db, query)
+            .stream_rows::<Row>()
+            .await
+            .expect("send request to server")
+            .try_collect::<Vec<Row>>()
+            .await
+            .expect("deserialize rows");
+
+        mock.assert_async().await;
+
+        assert_eq!(
+            rows,
+            vec![
+                Row {
+                    host: "a".into(),
+                    val: 1
+                },
+                Row {
+                    host: "b".into(),
+                    val: 2
+                },
+                Row {
+                    host: "c".into(),
+                    val: 3
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn api_v3_query_sql_stream_rows_outlives_timeout() {
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/query_sql")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                for val in 0..4 {
+                    std::thread::sleep(Duration::from_millis(200));
+                    writeln!(w, "{{\"val\":{val}}}")?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        // The stream takes longer than the timeout in total, but each chunk arrives within it:
+        let client = Client::new(mock_server.url())
+            .expect("create client")
+            .with_timeout(Duration::from_millis(500))
+            .expect("set client timeout");
+
+        let rows = client
+            .api_v3_query_sql("stats", "SELECT * FROM foo")
+            .stream_rows::<serde_json::Value>()
+            .await
+            .expect("send request to server")
+            .try_collect::<Vec<_>>()
+            .await
+            .expect("stream all rows");
+
+        mock.assert_async().await;
+
+        assert_eq!(
+            rows,
+            (0..4).map(|val| json!({ "val": val })).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn query_csv() {
+        let db = "stats";
+        let query = "SELECT * FROM foo";
+        let body = "host,time,val\ns1,1970-01-01T00:00:00,1\ns2,1970-01-01T00:00:00,2\n";
+
+        let mut mock_server = Server::new_async().await;
+        let mock = mock_server
+            .mock("POST", "/api/v3/query_sql")
+            .match_body(Matcher::Json(serde_json::json!({
+                "db": db,
+                "q": query,
+                "format": "csv",
+                "params": null,
+            })))
+            .with_status(200)
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let client = Client::new(mock_server.url()).expect("create client");
+
+        let csv = client
+            .query_csv(db, query)
+            .await
+            .expect("send query_sql request");
+
+        assert_eq!(csv, body);
+
+        mock.assert_async().await;
+    }
+
     #[tokio::test]
     async fn api_v3_query_sql_params() {
         let db = "stats";
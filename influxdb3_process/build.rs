@@ -3,13 +3,25 @@
 //
 // https://stackoverflow.com/questions/43753491/include-git-commit-hash-as-string-into-rust-program
 use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-env-changed=GIT_HASH");
+    // Rerun when the checkout changes, so that the hash and build timestamp are not left stale
+    // by incremental builds. Edits that are not yet staged are not picked up until they are.
+    for git_path in ["HEAD", "index"] {
+        if let Some(path) = get_git_path(git_path) {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
     // Populate env!(GIT_HASH) with the current git commit
     println!("cargo:rustc-env=GIT_HASH={}", get_git_hash());
     // Populate env!(GIT_HASH_SHORT) with the current git commit
     println!("cargo:rustc-env=GIT_HASH_SHORT={}", get_git_hash_short());
+    // Populate env!(BUILD_TIMESTAMP) with the build time, honoring SOURCE_DATE_EPOCH for
+    // reproducible builds
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", get_build_timestamp());
 
     Ok(())
 }
@@ -31,6 +43,16 @@ fn get_git_hash() -> String {
     out
 }
 
+fn get_git_path(name: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", name])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let path = String::from_utf8(output.stdout).ok()?;
+    Some(path.trim().to_string())
+}
+
 fn get_git_hash_short() -> String {
     let output = Command::new("git")
         .args(["rev-parse", "--short", "HEAD"])
@@ -38,3 +60,15 @@ fn get_git_hash_short() -> String {
         .expect("failed to execute git rev-parse to read the current git hash");
     String::from_utf8(output.stdout).expect("non-utf8 found in git hash")
 }
+
+fn get_build_timestamp() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("system time is before the Unix epoch")
+                .as_secs()
+        })
+}
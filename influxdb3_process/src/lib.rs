@@ -47,6 +47,10 @@ pub static INFLUXDB3_GIT_HASH_SHORT: &str = env!(
     "Can not find find GIT HASH in build environment"
 );
 
+/// Build time, in seconds since the Unix epoch, or `None` if the build did not record a valid one.
+pub static INFLUXDB3_BUILD_TIMESTAMP: Lazy<Option<i64>> =
+    Lazy::new(|| env!("BUILD_TIMESTAMP").parse().ok());
+
 /// Version string that is combined from [`INFLUXDB3_VERSION`] and [`INFLUXDB3_GIT_HASH`].
 pub static VERSION_STRING: Lazy<&'static str> = Lazy::new(|| {
     let s = format!(
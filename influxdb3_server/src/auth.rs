@@ -1,21 +1,134 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+
 use async_trait::async_trait;
 use authz::{Authorizer, Error, Permission};
-use observability_deps::tracing::{debug, warn};
+use hyper::header::HeaderValue;
+use hyper::Method;
+use observability_deps::tracing::{debug, info, warn};
 use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+
+use crate::http::TokenId;
+
+/// The default realm sent in the `WWW-Authenticate` header of `401` responses
+pub const DEFAULT_AUTH_REALM: &str = "influxdb3";
+
+/// The realm sent in the `WWW-Authenticate` challenge of `401` responses
+///
+/// The realm is sent as a quoted string, so quotes and backslashes in it are escaped. Parsing
+/// fails if the realm contains characters, such as control characters, that cannot be sent in
+/// a header.
+#[derive(Debug, Clone)]
+pub struct AuthRealm {
+    challenge: HeaderValue,
+}
+
+impl AuthRealm {
+    /// The `WWW-Authenticate` header value that challenges clients for a bearer token in
+    /// this realm
+    pub(crate) fn challenge(&self) -> HeaderValue {
+        self.challenge.clone()
+    }
+}
+
+impl Default for AuthRealm {
+    fn default() -> Self {
+        DEFAULT_AUTH_REALM
+            .parse()
+            .expect("the default auth realm is a valid header value")
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthRealmParseError {
+    #[error("invalid auth realm '{0}', the realm cannot contain control characters")]
+    Invalid(String),
+}
+
+impl FromStr for AuthRealm {
+    type Err = AuthRealmParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let escaped = s.replace('\\', r"\\").replace('"', r#"\""#);
+        HeaderValue::from_str(&format!(r#"Bearer realm="{escaped}""#))
+            .map(|challenge| Self { challenge })
+            .map_err(|_| AuthRealmParseError::Invalid(s.to_string()))
+    }
+}
+
+/// The tracing target of audit log events, which record every authorization decision
+pub const AUDIT_LOG_TARGET: &str = "influxdb3::audit";
+
+/// The outcome of authorizing a request, as recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AuthOutcome {
+    Authorized,
+    Unauthorized,
+    Forbidden,
+}
+
+impl AuthOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Authorized => "authorized",
+            Self::Unauthorized => "unauthorized",
+            Self::Forbidden => "forbidden",
+        }
+    }
+}
+
+/// Emit an audit log event for the authorization of a request
+///
+/// Only the [`TokenId`] is recorded, never the token itself.
+pub(crate) fn audit_auth_decision(
+    path: &str,
+    scheme: Option<&str>,
+    token_id: Option<&TokenId>,
+    client_addr: Option<SocketAddr>,
+    outcome: AuthOutcome,
+) {
+    info!(
+        target: AUDIT_LOG_TARGET,
+        path,
+        scheme = scheme.unwrap_or("none"),
+        token_id = token_id.map(TokenId::as_str).unwrap_or("none"),
+        client_ip = %client_addr.map(|addr| addr.ip().to_string()).unwrap_or_else(|| "unknown".to_string()),
+        outcome = outcome.as_str(),
+        "authorization decision"
+    );
+}
 
 /// An [`Authorizer`] implementation that will grant access to all
 /// requests that provide `token`
+///
+/// The token itself is never held in memory: the server is configured with the SHA-512
+/// hash of the token, which is salted with a random per-process salt and hashed again
+/// before being stored. Provided tokens are hashed the same way and compared against the
+/// stored hash in constant time.
 #[derive(Debug)]
 pub struct AllOrNothingAuthorizer {
-    token: Vec<u8>,
+    salt: [u8; 16],
+    salted_hash: Vec<u8>,
 }
 
 impl AllOrNothingAuthorizer {
-    pub fn new(token: Vec<u8>) -> Self {
-        Self { token }
+    /// Create a new authorizer from the SHA-512 hash of the token
+    pub fn new(token_hash: Vec<u8>) -> Self {
+        let salt = rand::random();
+        let salted_hash = salted_hash(&salt, &token_hash);
+        Self { salt, salted_hash }
     }
 }
 
+fn salted_hash(salt: &[u8], token_hash: &[u8]) -> Vec<u8> {
+    Sha512::new()
+        .chain_update(salt)
+        .chain_update(token_hash)
+        .finalize()
+        .to_vec()
+}
+
 #[async_trait]
 impl Authorizer for AllOrNothingAuthorizer {
     async fn permissions(
@@ -25,7 +138,8 @@ impl Authorizer for AllOrNothingAuthorizer {
     ) -> Result<Vec<Permission>, Error> {
         debug!(?perms, "requesting permissions");
         let provided = token.as_deref().ok_or(Error::NoToken)?;
-        if Sha512::digest(provided)[..] == self.token {
+        let provided_hash = salted_hash(&self.salt, &Sha512::digest(provided));
+        if bool::from(provided_hash.ct_eq(&self.salted_hash)) {
             Ok(perms.to_vec())
         } else {
             warn!("invalid token provided");
@@ -56,3 +170,146 @@ impl Authorizer for DefaultAuthorizer {
         Ok(())
     }
 }
+
+/// A rule for serving HTTP requests without authorization
+///
+/// Parsed either from the name of a resource, i.e., `health`, `ping`, or `metrics`,
+/// which disables authorization for all methods and paths serving that resource, or
+/// from a `<METHOD>:<path>` pair, e.g., `GET:/api/v3/query_sql`, which disables
+/// authorization only for requests using that method on that path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisableAuthz {
+    Health,
+    Ping,
+    Metrics,
+    Route { method: Method, path: String },
+}
+
+impl DisableAuthz {
+    /// The `(method, path)` pairs served by each named resource
+    const HEALTH_PATHS: &'static [(Method, &'static str)] =
+        &[(Method::GET, "/health"), (Method::GET, "/api/v1/health")];
+    const PING_PATHS: &'static [(Method, &'static str)] =
+        &[(Method::GET, "/ping"), (Method::POST, "/ping")];
+    const METRICS_PATHS: &'static [(Method, &'static str)] = &[(Method::GET, "/metrics")];
+
+    /// Check if this rule disables authorization for a request with the given `method`
+    /// and `path`
+    pub(crate) fn matches(&self, method: &Method, path: &str) -> bool {
+        let all_paths = match self {
+            Self::Health => Self::HEALTH_PATHS,
+            Self::Ping => Self::PING_PATHS,
+            Self::Metrics => Self::METRICS_PATHS,
+            Self::Route { method: m, path: p } => return m == method && p == path,
+        };
+        all_paths.iter().any(|(m, p)| m == method && *p == path)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DisableAuthzParseError {
+    #[error(
+        "invalid disable-authz rule '{0}', expected one of 'health', 'ping', \
+        'metrics', or a '<METHOD>:<path>' pair, e.g., 'GET:/api/v3/query_sql'"
+    )]
+    Invalid(String),
+}
+
+impl FromStr for DisableAuthz {
+    type Err = DisableAuthzParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "health" => Ok(Self::Health),
+            "ping" => Ok(Self::Ping),
+            "metrics" => Ok(Self::Metrics),
+            _ => {
+                let (method, path) = s
+                    .split_once(':')
+                    .filter(|(_, path)| path.starts_with('/'))
+                    .ok_or_else(|| DisableAuthzParseError::Invalid(s.to_string()))?;
+                let method = Method::from_bytes(method.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| DisableAuthzParseError::Invalid(s.to_string()))?;
+                Ok(Self::Route {
+                    method,
+                    path: path.to_string(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use authz::Authorizer;
+    use hyper::Method;
+    use sha2::{Digest, Sha512};
+
+    use super::{AllOrNothingAuthorizer, AuthRealm, DisableAuthz};
+
+    #[tokio::test]
+    async fn all_or_nothing_stores_salted_hash() {
+        let token = b"apiv3_secret";
+        let token_hash = Sha512::digest(token).to_vec();
+        let authz = AllOrNothingAuthorizer::new(token_hash.clone());
+
+        assert_ne!(authz.salted_hash, token.to_vec());
+        assert_ne!(authz.salted_hash, token_hash);
+        // Salts are random, so the same token is stored differently each time:
+        assert_ne!(
+            authz.salted_hash,
+            AllOrNothingAuthorizer::new(token_hash).salted_hash
+        );
+
+        assert!(authz.permissions(Some(token.to_vec()), &[]).await.is_ok());
+        assert!(authz
+            .permissions(Some(b"apiv3_wrong".to_vec()), &[])
+            .await
+            .is_err());
+        assert!(authz.permissions(None, &[]).await.is_err());
+    }
+
+    #[test]
+    fn parse_disable_authz() {
+        assert_eq!(
+            "health".parse::<DisableAuthz>().unwrap(),
+            DisableAuthz::Health
+        );
+        assert_eq!(
+            "GET:/api/v3/query_sql".parse::<DisableAuthz>().unwrap(),
+            DisableAuthz::Route {
+                method: Method::GET,
+                path: "/api/v3/query_sql".to_string()
+            }
+        );
+        assert!("foo".parse::<DisableAuthz>().is_err());
+        assert!("GET:api/v3/query_sql".parse::<DisableAuthz>().is_err());
+    }
+
+    #[test]
+    fn parse_auth_realm() {
+        assert_eq!(
+            AuthRealm::default().challenge(),
+            r#"Bearer realm="influxdb3""#
+        );
+        assert_eq!(
+            r#"my "quoted" \realm"#.parse::<AuthRealm>().unwrap().challenge(),
+            r#"Bearer realm="my \"quoted\" \\realm""#
+        );
+        assert!("bad\nrealm".parse::<AuthRealm>().is_err());
+        assert!("bad\u{7f}realm".parse::<AuthRealm>().is_err());
+    }
+
+    #[test]
+    fn disable_authz_matches() {
+        assert!(DisableAuthz::Ping.matches(&Method::GET, "/ping"));
+        assert!(DisableAuthz::Ping.matches(&Method::POST, "/ping"));
+        assert!(DisableAuthz::Health.matches(&Method::GET, "/api/v1/health"));
+        assert!(!DisableAuthz::Metrics.matches(&Method::GET, "/ping"));
+
+        let query_get = "GET:/api/v3/query_sql".parse::<DisableAuthz>().unwrap();
+        assert!(query_get.matches(&Method::GET, "/api/v3/query_sql"));
+        assert!(!query_get.matches(&Method::POST, "/api/v3/query_sql"));
+        assert!(!query_get.matches(&Method::POST, "/api/v3/write_lp"));
+    }
+}
@@ -1,8 +1,14 @@
-use std::sync::Arc;
+use std::{num::NonZeroU32, sync::Arc, time::Duration};
 
 use authz::Authorizer;
+use hyper::header::HeaderName;
+use influxdb3_write::Persister;
 
-use crate::{auth::DefaultAuthorizer, http::HttpApi, CommonServerState, Server};
+use crate::{
+    auth::{AuthRealm, DefaultAuthorizer, DisableAuthz},
+    http::HttpApi,
+    CommonServerState, Server,
+};
 
 #[derive(Debug)]
 pub struct ServerBuilder<W, Q, P, T> {
@@ -13,6 +19,14 @@ pub struct ServerBuilder<W, Q, P, T> {
     query_executor: Q,
     persister: P,
     authorizer: Arc<dyn Authorizer>,
+    authenticates_tokens: bool,
+    auth_realm: AuthRealm,
+    auth_header_name: Option<HeaderName>,
+    disable_authz: Vec<DisableAuthz>,
+    default_db: Option<String>,
+    rate_limit_per_token: Option<NonZeroU32>,
+    health_buffer_threshold_percent: Option<u8>,
+    query_timeout: Option<Duration>,
 }
 
 impl ServerBuilder<NoWriteBuf, NoQueryExec, NoPersister, NoTimeProvider> {
@@ -25,6 +39,14 @@ impl ServerBuilder<NoWriteBuf, NoQueryExec, NoPersister, NoTimeProvider> {
             query_executor: NoQueryExec,
             persister: NoPersister,
             authorizer: Arc::new(DefaultAuthorizer),
+            authenticates_tokens: false,
+            auth_realm: AuthRealm::default(),
+            auth_header_name: None,
+            disable_authz: vec![],
+            default_db: None,
+            rate_limit_per_token: None,
+            health_buffer_threshold_percent: None,
+            query_timeout: None,
         }
     }
 }
@@ -35,8 +57,59 @@ impl<W, Q, P, T> ServerBuilder<W, Q, P, T> {
         self
     }
 
+    /// Authorize requests with the given authorizer, in place of the default, which accepts
+    /// every request
+    ///
+    /// Only requests authorized by a token that this authorizer validates are attributed to
+    /// their token in request metrics, and limited by [`Self::rate_limit_per_token`].
     pub fn authorizer(mut self, a: Arc<dyn Authorizer>) -> Self {
         self.authorizer = a;
+        self.authenticates_tokens = true;
+        self
+    }
+
+    /// Set the realm sent in the `WWW-Authenticate` header of `401` responses
+    pub fn auth_realm(mut self, realm: AuthRealm) -> Self {
+        self.auth_realm = realm;
+        self
+    }
+
+    /// Set an alternative header that credentials may be given in, for deployments where
+    /// the `Authorization` header does not reach the server
+    pub fn auth_header_name(mut self, name: Option<HeaderName>) -> Self {
+        self.auth_header_name = name;
+        self
+    }
+
+    /// Serve the requests matched by the given rules without authorization
+    pub fn disable_authz(mut self, rules: Vec<DisableAuthz>) -> Self {
+        self.disable_authz = rules;
+        self
+    }
+
+    /// Set the database used by write and query requests that do not specify one
+    pub fn default_db(mut self, db: Option<String>) -> Self {
+        self.default_db = db;
+        self
+    }
+
+    /// Limit the number of requests per second that can be made with each token validated by
+    /// the [authorizer](Self::authorizer)
+    pub fn rate_limit_per_token(mut self, limit: Option<NonZeroU32>) -> Self {
+        self.rate_limit_per_token = limit;
+        self
+    }
+
+    /// Report the server as unhealthy once the write buffer is filled past the given
+    /// percentage of its memory limit
+    pub fn health_buffer_threshold_percent(mut self, percent: Option<u8>) -> Self {
+        self.health_buffer_threshold_percent = percent;
+        self
+    }
+
+    /// Set the timeout for queries that do not specify their own
+    pub fn query_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.query_timeout = timeout;
         self
     }
 }
@@ -68,6 +141,14 @@ impl<Q, P, T> ServerBuilder<NoWriteBuf, Q, P, T> {
             query_executor: self.query_executor,
             persister: self.persister,
             authorizer: self.authorizer,
+            authenticates_tokens: self.authenticates_tokens,
+            auth_realm: self.auth_realm,
+            auth_header_name: self.auth_header_name,
+            disable_authz: self.disable_authz,
+            default_db: self.default_db,
+            rate_limit_per_token: self.rate_limit_per_token,
+            health_buffer_threshold_percent: self.health_buffer_threshold_percent,
+            query_timeout: self.query_timeout,
         }
     }
 }
@@ -82,6 +163,14 @@ impl<W, P, T> ServerBuilder<W, NoQueryExec, P, T> {
             query_executor: WithQueryExec(qe),
             persister: self.persister,
             authorizer: self.authorizer,
+            authenticates_tokens: self.authenticates_tokens,
+            auth_realm: self.auth_realm,
+            auth_header_name: self.auth_header_name,
+            disable_authz: self.disable_authz,
+            default_db: self.default_db,
+            rate_limit_per_token: self.rate_limit_per_token,
+            health_buffer_threshold_percent: self.health_buffer_threshold_percent,
+            query_timeout: self.query_timeout,
         }
     }
 }
@@ -96,6 +185,14 @@ impl<W, Q, T> ServerBuilder<W, Q, NoPersister, T> {
             query_executor: self.query_executor,
             persister: WithPersister(p),
             authorizer: self.authorizer,
+            authenticates_tokens: self.authenticates_tokens,
+            auth_realm: self.auth_realm,
+            auth_header_name: self.auth_header_name,
+            disable_authz: self.disable_authz,
+            default_db: self.default_db,
+            rate_limit_per_token: self.rate_limit_per_token,
+            health_buffer_threshold_percent: self.health_buffer_threshold_percent,
+            query_timeout: self.query_timeout,
         }
     }
 }
@@ -110,12 +207,22 @@ impl<W, Q, P> ServerBuilder<W, Q, P, NoTimeProvider> {
             query_executor: self.query_executor,
             persister: self.persister,
             authorizer: self.authorizer,
+            authenticates_tokens: self.authenticates_tokens,
+            auth_realm: self.auth_realm,
+            auth_header_name: self.auth_header_name,
+            disable_authz: self.disable_authz,
+            default_db: self.default_db,
+            rate_limit_per_token: self.rate_limit_per_token,
+            health_buffer_threshold_percent: self.health_buffer_threshold_percent,
+            query_timeout: self.query_timeout,
         }
     }
 }
 
 impl<W, Q, P, T>
     ServerBuilder<WithWriteBuf<W>, WithQueryExec<Q>, WithPersister<P>, WithTimeProvider<T>>
+where
+    P: Persister,
 {
     pub fn build(self) -> Server<W, Q, P, T> {
         let persister = Arc::clone(&self.persister.0);
@@ -125,8 +232,17 @@ impl<W, Q, P, T>
             Arc::clone(&self.time_provider.0),
             Arc::clone(&self.write_buffer.0),
             Arc::clone(&self.query_executor.0),
+            persister.object_store(),
             self.max_request_size,
             Arc::clone(&authorizer),
+            self.authenticates_tokens,
+            self.auth_realm,
+            self.auth_header_name,
+            self.disable_authz,
+            self.default_db,
+            self.rate_limit_per_token,
+            self.health_buffer_threshold_percent,
+            self.query_timeout,
         ));
         Server {
             common_state: self.common_state,
@@ -1,10 +1,22 @@
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use arrow_flight::flight_service_server::{
     FlightService as Flight, FlightServiceServer as FlightServer,
 };
 use authz::Authorizer;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::future::BoxFuture;
+use hyper::header::{HeaderName, AUTHORIZATION};
+use hyper::{header::HeaderValue, Body, HeaderMap, Request, Response};
 use iox_query::QueryDatabase;
+use serde::Serialize;
+use tonic::body::BoxBody;
+use tower::Service;
+
+use crate::auth::{audit_auth_decision, AuthOutcome};
+use crate::http::{normalize_auth_header, validate_auth_header, TokenId};
+use crate::service::ClientAddr;
 
 pub(crate) fn make_flight_server<Q: QueryDatabase>(
     server: Arc<Q>,
@@ -12,3 +24,209 @@ pub(crate) fn make_flight_server<Q: QueryDatabase>(
 ) -> FlightServer<impl Flight> {
     service_grpc_flight::make_server(server, authz)
 }
+
+/// Wraps a gRPC service to authorize requests before they are passed to it
+///
+/// This is the only place gRPC requests are authorized, so the wrapped service should be
+/// created without an authorizer. Tokens are read from the same headers as on the HTTP API,
+/// and can be given with the Bearer or ApiKey schemes, or the Basic scheme, in which case
+/// the password is used as the token.
+///
+/// Requests that fail authorization are answered with a [`tonic::Status`] carrying
+/// a JSON error detail, so that clients can tell why the request was rejected, e.g.,
+/// a misspelled auth scheme versus an invalid token.
+#[derive(Debug, Clone)]
+pub(crate) struct GrpcAuthService<S> {
+    inner: S,
+    authorizer: Arc<dyn Authorizer>,
+    auth_header_name: Option<HeaderName>,
+}
+
+impl<S> GrpcAuthService<S> {
+    pub(crate) fn new(
+        inner: S,
+        authorizer: Arc<dyn Authorizer>,
+        auth_header_name: Option<HeaderName>,
+    ) -> Self {
+        Self {
+            inner,
+            authorizer,
+            auth_header_name,
+        }
+    }
+}
+
+impl<S> Service<Request<Body>> for GrpcAuthService<S>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        // The inner service was driven to readiness by `poll_ready`, so take it
+        // and leave a fresh clone in its place:
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authorizer = Arc::clone(&self.authorizer);
+        normalize_auth_header(req.headers_mut(), self.auth_header_name.as_ref());
+        Box::pin(async move {
+            let scheme = req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|header| header.to_str().ok())
+                .and_then(|header| header.split(' ').next())
+                .map(ToOwned::to_owned);
+            let token = auth_token(req.headers());
+            let token_id = token
+                .as_ref()
+                .ok()
+                .and_then(|token| token.as_deref())
+                .map(TokenId::new);
+            let result = match token {
+                Ok(token) => authorize(authorizer.as_ref(), token.clone())
+                    .await
+                    .map(|()| token),
+                Err(e) => Err(e),
+            };
+            audit_auth_decision(
+                req.uri().path(),
+                scheme.as_deref(),
+                token_id.as_ref(),
+                req.extensions().get::<ClientAddr>().map(|addr| addr.0),
+                match result {
+                    Ok(_) => AuthOutcome::Authorized,
+                    Err(GrpcAuthError::Forbidden) => AuthOutcome::Forbidden,
+                    Err(_) => AuthOutcome::Unauthorized,
+                },
+            );
+            let token = match result {
+                Ok(token) => token,
+                Err(e) => return Ok(e.into_status().to_http()),
+            };
+
+            // The credentials are not needed past this point, so keep them out of the inner
+            // service and anything it logs:
+            req.headers_mut().remove(AUTHORIZATION);
+            let is_handshake = req.uri().path() == HANDSHAKE_PATH;
+            let mut response = inner.call(req).await?;
+
+            // Flight clients that authenticate with a Basic handshake expect to be given the
+            // Bearer token to use on subsequent requests:
+            if scheme.as_deref() == Some("Basic") && is_handshake {
+                if let Some(bearer) = token.and_then(|token| {
+                    HeaderValue::from_bytes(&[b"Bearer ", token.as_slice()].concat()).ok()
+                }) {
+                    response.headers_mut().insert(AUTHORIZATION, bearer);
+                }
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// The path of the Flight `Handshake` method
+const HANDSHAKE_PATH: &str = "/arrow.flight.protocol.FlightService/Handshake";
+
+/// The reason a gRPC request failed authorization
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum GrpcAuthError {
+    #[error("no 'authorization' header was provided")]
+    MissingHeader,
+    #[error(
+        "the 'authorization' header must be in the form 'Bearer <token>', 'ApiKey <token>', \
+        or 'Basic <base64 username:password>'"
+    )]
+    MalformedHeader,
+    #[error(
+        "the 'authorization' header used an unknown auth scheme, expected 'Bearer', 'ApiKey', \
+        or 'Basic'"
+    )]
+    UnknownScheme,
+    #[error("the provided token is not valid")]
+    InvalidToken,
+    #[error("the provided token does not grant access to the requested resource")]
+    Forbidden,
+}
+
+#[derive(Debug, Serialize)]
+struct GrpcAuthErrorDetail {
+    error: GrpcAuthError,
+    message: String,
+}
+
+impl GrpcAuthError {
+    fn into_status(self) -> tonic::Status {
+        let code = match self {
+            Self::MissingHeader | Self::MalformedHeader | Self::UnknownScheme => {
+                tonic::Code::Unauthenticated
+            }
+            Self::InvalidToken | Self::Forbidden => tonic::Code::PermissionDenied,
+        };
+        let detail = serde_json::to_vec(&GrpcAuthErrorDetail {
+            error: self,
+            message: self.to_string(),
+        })
+        .expect("serialize gRPC auth error detail");
+        tonic::Status::with_details(code, self.to_string(), detail.into())
+    }
+}
+
+/// Extract the token from the `authorization` header, if one was provided
+///
+/// The Bearer and ApiKey schemes are validated as they are on the HTTP API. With the Basic
+/// scheme, as used by Flight clients that perform a username/password handshake, the
+/// password is taken as the token and the username is ignored.
+fn auth_token(headers: &HeaderMap) -> Result<Option<Vec<u8>>, GrpcAuthError> {
+    headers
+        .get(AUTHORIZATION)
+        .map(|header| {
+            let value = header
+                .to_str()
+                .map_err(|_| GrpcAuthError::MalformedHeader)?;
+            match value.split(' ').collect::<Vec<_>>().as_slice() {
+                ["Basic", credentials] => basic_auth_password(credentials),
+                ["Bearer" | "ApiKey", ..] => validate_auth_header(header.clone())
+                    .ok()
+                    .filter(|token| !token.is_empty())
+                    .ok_or(GrpcAuthError::MalformedHeader),
+                ["Basic", ..] | [_] => Err(GrpcAuthError::MalformedHeader),
+                _ => Err(GrpcAuthError::UnknownScheme),
+            }
+        })
+        .transpose()
+}
+
+fn basic_auth_password(credentials: &str) -> Result<Vec<u8>, GrpcAuthError> {
+    let decoded = STANDARD
+        .decode(credentials)
+        .map_err(|_| GrpcAuthError::MalformedHeader)?;
+    match decoded.iter().position(|b| *b == b':') {
+        Some(i) if i + 1 < decoded.len() => Ok(decoded[i + 1..].to_vec()),
+        _ => Err(GrpcAuthError::MalformedHeader),
+    }
+}
+
+async fn authorize(
+    authorizer: &dyn Authorizer,
+    token: Option<Vec<u8>>,
+) -> Result<(), GrpcAuthError> {
+    let token_provided = token.is_some();
+
+    authorizer
+        .permissions(token, &[])
+        .await
+        .map(|_| ())
+        .map_err(|e| match e {
+            authz::Error::Forbidden => GrpcAuthError::Forbidden,
+            _ if !token_provided => GrpcAuthError::MissingHeader,
+            _ => GrpcAuthError::InvalidToken,
+        })
+}
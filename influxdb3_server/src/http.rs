@@ -1,31 +1,49 @@
 //! HTTP API service implementations for `server`
 
+use crate::auth::{audit_auth_decision, AuthOutcome, AuthRealm, DisableAuthz};
+use crate::http::rate_limit::TokenRateLimiter;
+use crate::service::ClientAddr;
 use crate::{query_executor, QueryKind};
 use crate::{CommonServerState, QueryExecutor};
+use arrow::datatypes::{Schema as ArrowSchema, SchemaRef};
+use arrow::ipc::writer::StreamWriter;
 use arrow::record_batch::RecordBatch;
 use arrow::util::pretty;
+use async_compression::tokio::bufread::GzipEncoder;
 use authz::http::AuthorizationHeaderExtension;
 use authz::Authorizer;
 use bytes::{Bytes, BytesMut};
 use data_types::NamespaceName;
+use datafusion::common::utils::datafusion_strsim::levenshtein;
+use datafusion::common::SchemaError;
 use datafusion::error::DataFusionError;
 use datafusion::execution::memory_pool::UnboundedMemoryPool;
 use datafusion::execution::RecordBatchStream;
 use datafusion::physical_plan::SendableRecordBatchStream;
 use futures::{StreamExt, TryStreamExt};
+use hyper::header::HeaderName;
 use hyper::header::ACCEPT;
+use hyper::header::ACCEPT_ENCODING;
 use hyper::header::AUTHORIZATION;
 use hyper::header::CONTENT_ENCODING;
+use hyper::header::CONTENT_LENGTH;
 use hyper::header::CONTENT_TYPE;
+use hyper::header::RETRY_AFTER;
+use hyper::header::WWW_AUTHENTICATE;
 use hyper::http::HeaderValue;
 use hyper::HeaderMap;
-use hyper::{Body, Method, Request, Response, StatusCode};
-use influxdb3_process::{INFLUXDB3_GIT_HASH_SHORT, INFLUXDB3_VERSION};
+use hyper::{Body, Method, Request, Response, StatusCode, Uri};
+use influxdb3_process::{
+    INFLUXDB3_BUILD_TIMESTAMP, INFLUXDB3_GIT_HASH, INFLUXDB3_GIT_HASH_SHORT, INFLUXDB3_VERSION,
+    PROCESS_START_TIME,
+};
+use influxdb3_write::catalog::Catalog;
 use influxdb3_write::catalog::Error as CatalogError;
 use influxdb3_write::persister::TrackedMemoryArrowWriter;
 use influxdb3_write::write_buffer::Error as WriteBufferError;
 use influxdb3_write::BufferedWriteRequest;
 use influxdb3_write::Precision;
+use influxdb3_write::Wal;
 use influxdb3_write::WriteBuffer;
 use iox_http::write::single_tenant::SingleTenantRequestUnifier;
 use iox_http::write::v1::V1_NAMESPACE_RP_SEPARATOR;
@@ -33,19 +51,29 @@ use iox_http::write::{WriteParseError, WriteRequestUnifier};
 use iox_query_influxql_rewrite as rewrite;
 use iox_query_params::StatementParams;
 use iox_time::TimeProvider;
+use metric::{Attributes, DurationHistogram, Metric, U64Counter};
+use object_store::ObjectStore;
 use observability_deps::tracing::{debug, error, info};
-use serde::de::DeserializeOwned;
+use schema::{InfluxColumnType, InfluxFieldType, Schema};
+use serde::de::value::StrDeserializer;
+use serde::de::{DeserializeOwned, Deserializer, IntoDeserializer};
 use serde::Deserialize;
 use serde::Serialize;
+use sha2::{Digest, Sha512};
+use std::borrow::Cow;
 use std::convert::Infallible;
 use std::fmt::Debug;
+use std::num::NonZeroU32;
 use std::pin::Pin;
 use std::str::Utf8Error;
 use std::string::FromUtf8Error;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio_util::io::{ReaderStream, StreamReader};
 use unicode_segmentation::UnicodeSegmentation;
 
+mod rate_limit;
 mod v1;
 
 #[derive(Debug, Error)]
@@ -81,6 +109,13 @@ pub enum Error {
     #[error("invalid mime type ({0})")]
     InvalidMimeType(String),
 
+    /// The content type of a write request is not one that line protocol can be sent as
+    #[error(
+        "unsupported content type for a line protocol write ({0}), \
+        expected text/plain or no content type"
+    )]
+    UnsupportedWriteContentType(String),
+
     /// NamespaceName validation error.
     #[error("error validating namespace name: {0}")]
     InvalidNamespaceName(#[from] data_types::NamespaceNameError),
@@ -94,14 +129,6 @@ pub enum Error {
     #[error("this service is overloaded, please try again later")]
     RequestLimit,
 
-    /// The request has no authentication, but authorization is configured.
-    #[error("authentication required")]
-    Unauthenticated,
-
-    /// The provided authorization is not sufficient to perform the request.
-    #[error("access denied")]
-    Forbidden,
-
     /// The HTTP request method is not supported for this resource
     #[error("unsupported method")]
     UnsupportedMethod,
@@ -114,9 +141,45 @@ pub enum Error {
     #[error("missing query parameters 'db' and 'q'")]
     MissingQueryParams,
 
-    /// MIssing parameters for write
-    #[error("missing query parameter 'db'")]
-    MissingWriteParams,
+    /// No `db` parameter was provided, and no default database is configured
+    #[error("missing required parameter: db")]
+    MissingDb,
+
+    /// No `table` parameter was provided
+    #[error("missing required parameter: table")]
+    MissingTable,
+
+    /// The requested database does not exist
+    #[error("database not found: {0}")]
+    DatabaseNotFound(String),
+
+    /// The requested table does not exist in the database
+    #[error("table not found: {table} in database {db}")]
+    TableNotFound { db: String, table: String },
+
+    /// The query parameters of a write request could not be parsed
+    #[error("invalid write parameters: {0}")]
+    InvalidWriteParams(serde_urlencoded::de::Error),
+
+    /// The query parameters of a health request could not be parsed
+    #[error("invalid health parameters: {0}")]
+    InvalidHealthParams(serde_urlencoded::de::Error),
+
+    /// The query parameters of a table schema request could not be parsed
+    #[error("invalid table schema parameters: {0}")]
+    InvalidTableSchemaParams(serde_urlencoded::de::Error),
+
+    /// The `consistency` parameter given to a v1 write was not a known level
+    #[error("invalid consistency parameter, expected one of: any, one, quorum, all")]
+    InvalidConsistency,
+
+    /// The `timeout` parameter given to a query could not be parsed as a duration
+    #[error("invalid query timeout: {0}")]
+    InvalidQueryTimeout(humantime::DurationError),
+
+    /// The query did not complete within its timeout, and was cancelled
+    #[error("query exceeded the timeout of {}", humantime::format_duration(*.0))]
+    QueryTimeout(Duration),
 
     #[error("the mime type specified was not valid UTF8: {0}")]
     NonUtf8MimeType(#[from] FromUtf8Error),
@@ -149,6 +212,14 @@ pub enum Error {
     #[error("serde json error: {0}")]
     SerdeJson(#[from] serde_json::Error),
 
+    /// The JSON body of a request could not be parsed
+    #[error("invalid request body: {0}")]
+    InvalidRequestBody(#[source] serde_json::Error),
+
+    /// The JSON `params` query parameter of a request could not be parsed
+    #[error("invalid params parameter: {0}")]
+    InvalidParams(#[source] serde_json::Error),
+
     // Influxdb3 Write
     #[error("serde json error: {0}")]
     Influxdb3Write(#[from] influxdb3_write::Error),
@@ -160,7 +231,25 @@ pub enum Error {
     Io(#[from] std::io::Error),
 
     #[error("query error: {0}")]
-    Query(#[from] query_executor::Error),
+    Query(#[source] query_executor::Error),
+
+    /// The query referenced a column that does not exist in the tables it selects from
+    #[error("column '{column}' does not exist{}", did_you_mean(suggestions))]
+    UndefinedColumn {
+        column: String,
+        suggestions: Vec<String>,
+    },
+
+    /// The query would scan more chunks of a table than are allowed per query
+    #[error(
+        "query would scan {chunks} chunks of table {table_name}, \
+        which exceeds the limit of {limit} chunks per query"
+    )]
+    ChunkLimit {
+        table_name: String,
+        chunks: usize,
+        limit: usize,
+    },
 
     #[error(transparent)]
     DbName(#[from] ValidateDbNameError),
@@ -193,7 +282,10 @@ pub enum Error {
 pub enum AuthorizationError {
     #[error("the request was not authorized")]
     Unauthorized,
-    #[error("the request was not in the form of 'Authorization: Bearer <token>'")]
+    #[error(
+        "the request was not in the form of 'Authorization: <auth-scheme> <token>', \
+        supported auth-schemes are Bearer and ApiKey"
+    )]
     MalformedRequest,
     #[error("requestor is forbidden from requested resource")]
     Forbidden,
@@ -239,6 +331,30 @@ impl Error {
                     .body(body)
                     .unwrap()
             }
+            Self::MissingDb
+            | Self::MissingTable
+            | Self::InvalidConsistency
+            | Self::InvalidRequestBody(_)
+            | Self::InvalidParams(_)
+            | Self::InvalidGzip(_)
+            | Self::InvalidQueryTimeout(_)
+            | Self::InvalidWriteParams(_)
+            | Self::InvalidHealthParams(_)
+            | Self::InvalidTableSchemaParams(_)
+            | Self::InfluxqlNoDatabase
+            | Self::UndefinedColumn { .. }
+            | Self::ChunkLimit { .. } => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(body)
+                    .unwrap()
+            }
             Self::DbName(e) => {
                 let err: ErrorMessage<()> = ErrorMessage {
                     error: e.to_string(),
@@ -275,6 +391,54 @@ impl Error {
                     .body(body)
                     .unwrap()
             }
+            Self::DatabaseNotFound(_) | Self::TableNotFound { .. } => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(body)
+                    .unwrap()
+            }
+            Self::QueryTimeout(_) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::REQUEST_TIMEOUT)
+                    .body(body)
+                    .unwrap()
+            }
+            Self::RequestSizeExceeded(_) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::PAYLOAD_TOO_LARGE)
+                    .body(body)
+                    .unwrap()
+            }
+            Self::UnsupportedWriteContentType(_) => {
+                let err: ErrorMessage<()> = ErrorMessage {
+                    error: self.to_string(),
+                    data: None,
+                };
+                let serialized = serde_json::to_string(&err).unwrap();
+                let body = Body::from(serialized);
+                Response::builder()
+                    .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                    .body(body)
+                    .unwrap()
+            }
             _ => {
                 let body = Body::from(self.to_string());
                 Response::builder()
@@ -286,6 +450,95 @@ impl Error {
     }
 }
 
+impl From<query_executor::Error> for Error {
+    /// Planning errors for columns that do not exist, and queries that would scan too many
+    /// chunks, are surfaced as [`Error::UndefinedColumn`] and [`Error::ChunkLimit`],
+    /// so that they are reported to the client as a bad request
+    fn from(e: query_executor::Error) -> Self {
+        if let query_executor::Error::QueryPlanning(df_err)
+        | query_executor::Error::ExecuteStream(df_err) = &e
+        {
+            // The chunk limit is checked when tables are scanned, so it is wrapped as an
+            // external error by DataFusion:
+            if let DataFusionError::External(err) = df_err.find_root() {
+                if let Some(query_executor::Error::ChunkLimit {
+                    table_name,
+                    chunks,
+                    limit,
+                }) = err.downcast_ref::<query_executor::Error>()
+                {
+                    return Self::ChunkLimit {
+                        table_name: table_name.clone(),
+                        chunks: *chunks,
+                        limit: *limit,
+                    };
+                }
+            }
+        }
+        if let query_executor::Error::QueryPlanning(df_err) = &e {
+            if let DataFusionError::SchemaError(
+                SchemaError::FieldNotFound {
+                    field,
+                    valid_fields,
+                },
+                _,
+            ) = df_err.find_root()
+            {
+                return Self::UndefinedColumn {
+                    column: field.name.clone(),
+                    suggestions: suggest_columns(
+                        &field.name,
+                        valid_fields.iter().map(|c| c.name.as_str()),
+                    ),
+                };
+            }
+        }
+        Self::Query(e)
+    }
+}
+
+/// Format the suggestions for an [`Error::UndefinedColumn`], if there are any
+fn did_you_mean(suggestions: &[String]) -> String {
+    match suggestions {
+        [] => String::new(),
+        [only] => format!(", did you mean '{only}'?"),
+        [rest @ .., last] => {
+            let rest = rest
+                .iter()
+                .map(|s| format!("'{s}'"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(", did you mean one of {rest} or '{last}'?")
+        }
+    }
+}
+
+/// The maximum number of columns suggested in place of an undefined column
+const MAX_COLUMN_SUGGESTIONS: usize = 3;
+
+/// How long the verbose health check waits for the object store to respond
+const OBJECT_STORE_HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Find the columns whose names are within a small edit distance of the undefined `column`,
+/// closest first
+fn suggest_columns<'a>(column: &str, valid: impl Iterator<Item = &'a str>) -> Vec<String> {
+    // Allow roughly one edit for every three characters, so that short names are not matched
+    // against everything:
+    let max_distance = (column.chars().count() / 3).max(1);
+    let column = column.to_lowercase();
+    let mut candidates: Vec<(usize, &str)> = valid
+        .map(|name| (levenshtein(&column, &name.to_lowercase()), name))
+        .filter(|(d, _)| *d <= max_distance)
+        .collect();
+    candidates.sort();
+    candidates.dedup_by(|a, b| a.1 == b.1);
+    candidates
+        .into_iter()
+        .take(MAX_COLUMN_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug)]
@@ -294,8 +547,19 @@ pub(crate) struct HttpApi<W, Q, T> {
     write_buffer: Arc<W>,
     time_provider: Arc<T>,
     pub(crate) query_executor: Arc<Q>,
+    object_store: Arc<dyn ObjectStore>,
     max_request_bytes: usize,
     authorizer: Arc<dyn Authorizer>,
+    /// Whether the authorizer validates tokens, rather than accepting any that are given
+    authenticates_tokens: bool,
+    auth_realm: AuthRealm,
+    auth_header_name: Option<HeaderName>,
+    disable_authz: Vec<DisableAuthz>,
+    default_db: Option<String>,
+    token_metrics: TokenMetrics,
+    rate_limiter: Option<TokenRateLimiter>,
+    health_buffer_threshold_percent: Option<u8>,
+    query_timeout: Option<Duration>,
     legacy_write_param_unifier: SingleTenantRequestUnifier,
 }
 
@@ -305,17 +569,37 @@ impl<W, Q, T> HttpApi<W, Q, T> {
         time_provider: Arc<T>,
         write_buffer: Arc<W>,
         query_executor: Arc<Q>,
+        object_store: Arc<dyn ObjectStore>,
         max_request_bytes: usize,
         authorizer: Arc<dyn Authorizer>,
+        authenticates_tokens: bool,
+        auth_realm: AuthRealm,
+        auth_header_name: Option<HeaderName>,
+        disable_authz: Vec<DisableAuthz>,
+        default_db: Option<String>,
+        rate_limit_per_token: Option<NonZeroU32>,
+        health_buffer_threshold_percent: Option<u8>,
+        query_timeout: Option<Duration>,
     ) -> Self {
         let legacy_write_param_unifier = SingleTenantRequestUnifier::new(Arc::clone(&authorizer));
+        let token_metrics = TokenMetrics::new(&common_state.metrics);
         Self {
             common_state,
             time_provider,
             write_buffer,
             query_executor,
+            object_store,
             max_request_bytes,
             authorizer,
+            authenticates_tokens,
+            auth_realm,
+            auth_header_name,
+            disable_authz,
+            default_db,
+            token_metrics,
+            rate_limiter: rate_limit_per_token.map(TokenRateLimiter::new),
+            health_buffer_threshold_percent,
+            query_timeout,
             legacy_write_param_unifier,
         }
     }
@@ -329,14 +613,21 @@ where
     Error: From<<Q as QueryExecutor>::Error>,
 {
     async fn write_lp(&self, req: Request<Body>) -> Result<Response<Body>> {
-        let query = req.uri().query().ok_or(Error::MissingWriteParams)?;
-        let params: WriteParams = serde_urlencoded::from_str(query)?;
+        let query = req.uri().query().unwrap_or_default();
+        let params: WriteParams =
+            serde_urlencoded::from_str(query).map_err(Error::InvalidWriteParams)?;
         self.write_lp_inner(params, req, false, false).await
     }
 
+    async fn write_v1(&self, params: WriteParams, req: Request<Body>) -> Result<Response<Body>> {
+        validate_v1_consistency(&req)?;
+        self.write_lp_inner(params, req, true, false).await
+    }
+
     async fn write_v3(&self, req: Request<Body>) -> Result<Response<Body>> {
-        let query = req.uri().query().ok_or(Error::MissingWriteParams)?;
-        let params: WriteParams = serde_urlencoded::from_str(query)?;
+        let query = req.uri().query().unwrap_or_default();
+        let params: WriteParams =
+            serde_urlencoded::from_str(query).map_err(Error::InvalidWriteParams)?;
         self.write_lp_inner(params, req, false, true).await
     }
 
@@ -347,13 +638,15 @@ where
         accept_rp: bool,
         use_v3: bool,
     ) -> Result<Response<Body>> {
-        validate_db_name(&params.db, accept_rp)?;
-        info!("write_lp to {}", params.db);
+        let db = self.resolve_db(params.db)?;
+        validate_db_name(&db, accept_rp)?;
+        info!("write_lp to {}", db);
 
+        validate_write_content_type(req.headers())?;
         let body = self.read_body(req).await?;
         let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
 
-        let database = NamespaceName::new(params.db)?;
+        let database = NamespaceName::new(db)?;
 
         let default_time = self.time_provider.now();
 
@@ -379,41 +672,86 @@ where
                 .await?
         };
 
-        if result.invalid_lines.is_empty() {
-            Ok(Response::new(Body::empty()))
-        } else {
-            Err(Error::PartialLpWrite(result))
+        if !result.invalid_lines.is_empty() {
+            return Err(Error::PartialLpWrite(result));
+        }
+
+        if !params.summary {
+            return Ok(Response::new(Body::empty()));
+        }
+
+        #[derive(Debug, Serialize)]
+        struct WriteSummary {
+            lines_written: usize,
+            bytes: usize,
         }
+
+        let body = serde_json::to_string(&WriteSummary {
+            lines_written: result.line_count,
+            bytes: body.len(),
+        })?;
+        Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(Into::into)
     }
 
     async fn query_sql(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let gzip = accepts_gzip(req.headers());
         let QueryRequest {
             database,
             query_str,
             format,
             params,
-        } = self.extract_query_request::<String>(req).await?;
+            schema,
+            timeout,
+        } = self.extract_query_request::<Option<String>>(req).await?;
+        let database = self.resolve_db(database)?;
+        let timeout = self.resolve_query_timeout(timeout.as_deref())?;
 
-        info!(%database, %query_str, ?format, "handling query_sql");
+        info!(%database, %query_str, ?format, ?timeout, "handling query_sql");
 
-        let stream = self
-            .query_executor
-            .query(&database, &query_str, params, QueryKind::Sql, None, None)
-            .await?;
+        let deadline = timeout.map(|timeout| (tokio::time::Instant::now() + timeout, timeout));
+        let execute = async {
+            let stream = self
+                .query_executor
+                .query(&database, &query_str, params, QueryKind::Sql, None, None)
+                .await?;
 
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, format.as_content_type())
-            .body(record_batch_stream_to_body(stream, format).await?)
-            .map_err(Into::into)
+            match format {
+                QueryFormat::Json if schema => {
+                    record_batch_stream_to_json_with_schema(stream).await
+                }
+                format => record_batch_stream_to_body(stream, format).await,
+            }
+        };
+
+        // Dropping the execution on timeout drops the record batch stream, which cancels the
+        // tasks that are running the query plan:
+        let body = match deadline {
+            Some((deadline, timeout)) => {
+                let body = tokio::time::timeout_at(deadline, execute)
+                    .await
+                    .map_err(|_| Error::QueryTimeout(timeout))??;
+                // Streamed formats produce their body after this returns, so the body is held
+                // to the same deadline:
+                body_with_deadline(body, deadline, timeout)
+            }
+            None => execute.await?,
+        };
+
+        query_response(format, body, gzip)
     }
 
     async fn query_influxql(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let gzip = accepts_gzip(req.headers());
         let QueryRequest {
             database,
             query_str,
             format,
             params,
+            ..
         } = self.extract_query_request::<Option<String>>(req).await?;
 
         info!(?database, %query_str, ?format, "handling query_influxql");
@@ -421,17 +759,197 @@ where
         let stream = self
             .query_influxql_inner(database, &query_str, params)
             .await?;
+        let body = record_batch_stream_to_body(stream, format).await?;
 
-        Response::builder()
-            .status(StatusCode::OK)
-            .header(CONTENT_TYPE, format.as_content_type())
-            .body(record_batch_stream_to_body(stream, format).await?)
-            .map_err(Into::into)
+        query_response(format, body, gzip)
     }
 
-    fn health(&self) -> Result<Response<Body>> {
-        let response_body = "OK";
-        Ok(Response::new(Body::from(response_body.to_string())))
+    /// Use the given query `timeout`, falling back to the configured default timeout if the
+    /// request did not provide one
+    fn resolve_query_timeout(&self, timeout: Option<&str>) -> Result<Option<Duration>> {
+        match timeout {
+            Some(timeout) => humantime::parse_duration(timeout)
+                .map(Some)
+                .map_err(Error::InvalidQueryTimeout),
+            None => Ok(self.query_timeout),
+        }
+    }
+
+    /// Use the given `db`, falling back to the configured default database if the
+    /// request did not provide one
+    fn resolve_db(&self, db: Option<String>) -> Result<String> {
+        db.or_else(|| self.default_db.clone())
+            .ok_or(Error::MissingDb)
+    }
+
+    /// Report the health of the server
+    ///
+    /// If a write buffer threshold is configured, and the buffer is filled past it, this
+    /// responds with a 503, so that load balancers can route writes to other servers until
+    /// the buffer has been persisted and the server recovers.
+    ///
+    /// With `verbose=true`, the response is a JSON document describing the server and the
+    /// status of each of its components.
+    async fn health(&self, req: Request<Body>) -> Result<Response<Body>> {
+        #[derive(Debug, Deserialize)]
+        struct HealthParams {
+            #[serde(default)]
+            verbose: bool,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct HealthResponse<'a> {
+            status: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            message: Option<String>,
+            version: &'a str,
+            revision: &'a str,
+            uptime_seconds: u64,
+            catalog: CatalogInfo,
+            checks: HealthChecks<'a>,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct CatalogInfo {
+            databases: usize,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct HealthChecks<'a> {
+            wal: ComponentHealth<'a>,
+            write_buffer: WriteBufferHealth<'a>,
+            object_store: ComponentHealth<'a>,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct ComponentHealth<'a> {
+            status: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            message: Option<String>,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct WriteBufferHealth<'a> {
+            status: &'a str,
+            size_bytes: usize,
+            limit_bytes: usize,
+        }
+
+        let query = req.uri().query().unwrap_or_default();
+        let params: HealthParams =
+            serde_urlencoded::from_str(query).map_err(Error::InvalidHealthParams)?;
+
+        let size = self.write_buffer.buffer_size_bytes();
+        let limit = self.write_buffer.buffer_limit_bytes();
+        let backpressure = self.health_buffer_threshold_percent.and_then(|threshold| {
+            let percent = size.saturating_mul(100) / limit.max(1);
+            (percent >= usize::from(threshold)).then(|| {
+                format!(
+                    "BACKPRESSURE: write buffer is {percent}% full, \
+                    exceeding the threshold of {threshold}%"
+                )
+            })
+        });
+
+        if !params.verbose {
+            let status = if backpressure.is_some() {
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            };
+            return Ok(Response::builder()
+                .status(status)
+                .body(Body::from(backpressure.unwrap_or_else(|| "OK".to_string())))
+                .unwrap());
+        }
+
+        // Only the verbose check reads the WAL directory, so that the plain check stays fast:
+        let wal = match self.write_buffer.wal().map(|wal| wal.segment_files()) {
+            None => ComponentHealth {
+                status: "disabled",
+                message: None,
+            },
+            Some(Ok(_)) => ComponentHealth {
+                status: "ok",
+                message: None,
+            },
+            Some(Err(e)) => ComponentHealth {
+                status: "error",
+                message: Some(format!("unable to read the WAL directory: {e}")),
+            },
+        };
+        // A single delimited listing of the store root is enough to show that it can be
+        // reached, and the timeout keeps a hung store from hanging the health check:
+        let object_store = match tokio::time::timeout(
+            OBJECT_STORE_HEALTH_TIMEOUT,
+            self.object_store.list_with_delimiter(None),
+        )
+        .await
+        {
+            Ok(Ok(_)) => ComponentHealth {
+                status: "ok",
+                message: None,
+            },
+            Ok(Err(e)) => ComponentHealth {
+                status: "error",
+                message: Some(format!("unable to list the object store: {e}")),
+            },
+            Err(_) => ComponentHealth {
+                status: "error",
+                message: Some(format!(
+                    "object store did not respond within {}s",
+                    OBJECT_STORE_HEALTH_TIMEOUT.as_secs()
+                )),
+            },
+        };
+        let (status_code, status, message) =
+            if let Some(message) = wal.message.clone().or_else(|| object_store.message.clone()) {
+                (StatusCode::SERVICE_UNAVAILABLE, "error", Some(message))
+            } else if backpressure.is_some() {
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "backpressure",
+                    backpressure.clone(),
+                )
+            } else {
+                (StatusCode::OK, "ok", None)
+            };
+
+        let uptime_seconds = self
+            .time_provider
+            .now()
+            .checked_duration_since(*PROCESS_START_TIME)
+            .unwrap_or_default()
+            .as_secs();
+        let body = serde_json::to_string(&HealthResponse {
+            status,
+            message,
+            version: &INFLUXDB3_VERSION,
+            revision: INFLUXDB3_GIT_HASH_SHORT,
+            uptime_seconds,
+            catalog: CatalogInfo {
+                databases: self.write_buffer.catalog().list_databases().len(),
+            },
+            checks: HealthChecks {
+                wal,
+                write_buffer: WriteBufferHealth {
+                    status: if backpressure.is_some() {
+                        "backpressure"
+                    } else {
+                        "ok"
+                    },
+                    size_bytes: size,
+                    limit_bytes: limit,
+                },
+                object_store,
+            },
+        })?;
+
+        Ok(Response::builder()
+            .status(status_code)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap())
     }
 
     fn ping(&self) -> Result<Response<Body>> {
@@ -439,23 +957,183 @@ where
         struct PingResponse<'a> {
             version: &'a str,
             revision: &'a str,
+            git_commit: &'a str,
+            build_date: Option<String>,
+            api_versions: &'a [&'a str],
         }
 
         let body = serde_json::to_string(&PingResponse {
             version: &INFLUXDB3_VERSION,
             revision: INFLUXDB3_GIT_HASH_SHORT,
+            git_commit: INFLUXDB3_GIT_HASH.trim(),
+            build_date: INFLUXDB3_BUILD_TIMESTAMP
+                .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0))
+                .map(|date| date.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)),
+            api_versions: &["v1", "v2", "v3"],
         })
         .unwrap();
 
         Ok(Response::new(Body::from(body)))
     }
 
-    fn handle_metrics(&self) -> Result<Response<Body>> {
+    fn capabilities(&self) -> Result<Response<Body>> {
+        #[derive(Debug, Serialize)]
+        struct CapabilitiesResponse<'a> {
+            version: &'a str,
+            revision: &'a str,
+            write: WriteCapabilities,
+            query: QueryCapabilities,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct WriteCapabilities {
+            formats: &'static [&'static str],
+            precisions: &'static [&'static str],
+            endpoints: &'static [&'static str],
+        }
+
+        #[derive(Debug, Serialize)]
+        struct QueryCapabilities {
+            languages: &'static [&'static str],
+            formats: &'static [&'static str],
+            endpoints: &'static [&'static str],
+        }
+
+        // These must be kept in sync with the `Precision` and `QueryFormat` types, and
+        // with the routes served by `route_request`:
+        let body = serde_json::to_string(&CapabilitiesResponse {
+            version: &INFLUXDB3_VERSION,
+            revision: INFLUXDB3_GIT_HASH_SHORT,
+            write: WriteCapabilities {
+                formats: &["line_protocol"],
+                precisions: &["auto", "second", "millisecond", "microsecond", "nanosecond"],
+                endpoints: &[
+                    "/api/v3/write_lp",
+                    "/api/v3/write",
+                    "/api/v2/write",
+                    "/write",
+                ],
+            },
+            query: QueryCapabilities {
+                languages: &["sql", "influxql"],
+                formats: &["json", "jsonl", "csv", "pretty", "parquet", "arrow"],
+                endpoints: &["/api/v3/query_sql", "/api/v3/query_influxql", "/query"],
+            },
+        })
+        .unwrap();
+
+        Ok(Response::new(Body::from(body)))
+    }
+
+    /// Describe the columns of a table, as recorded in the catalog
+    fn configure_table_schema(&self, req: Request<Body>) -> Result<Response<Body>> {
+        #[derive(Debug, Deserialize)]
+        struct TableSchemaParams {
+            db: Option<String>,
+            table: Option<String>,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct TableSchemaResponse<'a> {
+            db: &'a str,
+            table: &'a str,
+            columns: Vec<ColumnSchema<'a>>,
+        }
+
+        #[derive(Debug, Serialize)]
+        struct ColumnSchema<'a> {
+            name: &'a str,
+            kind: &'static str,
+            #[serde(rename = "type")]
+            data_type: &'static str,
+        }
+
+        let query = req.uri().query().unwrap_or_default();
+        let params: TableSchemaParams =
+            serde_urlencoded::from_str(query).map_err(Error::InvalidTableSchemaParams)?;
+        let db = self.resolve_db(params.db)?;
+        let table = params.table.ok_or(Error::MissingTable)?;
+
+        let db_schema = self
+            .write_buffer
+            .catalog()
+            .db_schema(&db)
+            .ok_or_else(|| Error::DatabaseNotFound(db.clone()))?;
+        let table_def = db_schema
+            .get_table(&table)
+            .ok_or_else(|| Error::TableNotFound {
+                db: db.clone(),
+                table: table.clone(),
+            })?;
+
+        let columns = table_def
+            .schema
+            .iter()
+            .map(|(col_type, field)| {
+                let (kind, data_type) = match col_type {
+                    InfluxColumnType::Tag => ("tag", "string"),
+                    InfluxColumnType::Timestamp => ("time", "timestamp"),
+                    InfluxColumnType::Field(field_type) => (
+                        "field",
+                        match field_type {
+                            InfluxFieldType::Float => "float",
+                            InfluxFieldType::Integer => "integer",
+                            InfluxFieldType::UInteger => "uinteger",
+                            InfluxFieldType::String => "string",
+                            InfluxFieldType::Boolean => "boolean",
+                        },
+                    ),
+                };
+                ColumnSchema {
+                    name: field.name(),
+                    kind,
+                    data_type,
+                }
+            })
+            .collect();
+        let body = serde_json::to_string(&TableSchemaResponse {
+            db: &db,
+            table: &table,
+            columns,
+        })?;
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap())
+    }
+
+    /// Report the server's metrics in the Prometheus text format, or in the OpenMetrics
+    /// text format if the client accepts it
+    fn handle_metrics(&self, req: Request<Body>) -> Result<Response<Body>> {
         let mut body: Vec<u8> = Default::default();
         let mut reporter = metric_exporters::PrometheusTextEncoder::new(&mut body);
         self.common_state.metrics.report(&mut reporter);
 
-        Ok(Response::new(Body::from(body)))
+        let openmetrics = req
+            .headers()
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .any(|media_type| {
+                media_type.split(';').next().map(str::trim) == Some("application/openmetrics-text")
+            });
+        let (content_type, body) = if openmetrics {
+            (
+                "application/openmetrics-text; version=1.0.0; charset=utf-8",
+                prometheus_to_openmetrics(&String::from_utf8_lossy(&body)).into_bytes(),
+            )
+        } else {
+            ("text/plain; version=0.0.4; charset=utf-8", body)
+        };
+
+        Ok(Response::builder()
+            .status(StatusCode::OK)
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap())
     }
 
     /// Parse the request's body into raw bytes, applying the configured size
@@ -472,6 +1150,16 @@ where
             Some(v) => return Err(Error::InvalidContentEncoding(v.to_string())),
         };
 
+        // Reject bodies that declare a length over the limit before reading any of it:
+        let content_length = req
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<usize>().ok());
+        if content_length.is_some_and(|len| len > self.max_request_bytes) {
+            return Err(Error::RequestSizeExceeded(self.max_request_bytes));
+        }
+
         let mut payload = req.into_body();
 
         let mut body = BytesMut::new();
@@ -515,7 +1203,21 @@ where
         Ok(decoded_data.into())
     }
 
+    /// The alternative header that credentials may be given in, if one is configured
+    pub(crate) fn auth_header_name(&self) -> Option<&HeaderName> {
+        self.auth_header_name.as_ref()
+    }
+
     async fn authorize_request(&self, req: &mut Request<Body>) -> Result<(), AuthorizationError> {
+        if self
+            .disable_authz
+            .iter()
+            .any(|rule| rule.matches(req.method(), req.uri().path()))
+        {
+            redact_credentials(req);
+            return Ok(());
+        }
+
         // Extend the request with the authorization token; this is used downstream in some
         // APIs, such as write, that need the full header value to authorize a request.
         let auth_header = req.headers().get(AUTHORIZATION).cloned();
@@ -525,13 +1227,20 @@ where
         let auth = if let Some(p) = extract_v1_auth_token(req) {
             Some(p)
         } else {
-            // We won't need the authorization header anymore and we don't want to accidentally log it.
-            // Take it out so we can use it and not log it later by accident.
-            req.headers_mut()
-                .remove(AUTHORIZATION)
+            req.headers()
+                .get(AUTHORIZATION)
+                .cloned()
                 .map(validate_auth_header)
                 .transpose()?
         };
+        // We won't need the credentials anymore and we don't want to accidentally log them.
+        redact_credentials(req);
+
+        // Extend the request with the id of the provided token, used to attribute
+        // audit log events and request metrics to the token
+        if let Some(token_id) = auth.as_deref().map(TokenId::new) {
+            req.extensions_mut().insert(token_id);
+        }
 
         // Currently we pass an empty permissions list, but in future we may be able to derive
         // the permissions based on the incoming request
@@ -558,12 +1267,18 @@ where
                     database: r.database,
                     query_str: r.query_str,
                     format: r.format,
-                    params: r.params.map(|s| serde_json::from_str(&s)).transpose()?,
+                    params: r
+                        .params
+                        .map(|s| serde_json::from_str(&s))
+                        .transpose()
+                        .map_err(Error::InvalidParams)?,
+                    schema: r.schema,
+                    timeout: r.timeout,
                 }
             }
             Method::POST => {
                 let body = self.read_body(req).await?;
-                serde_json::from_slice(body.as_ref())?
+                serde_json::from_slice(body.as_ref()).map_err(Error::InvalidRequestBody)?
             }
             _ => return Err(Error::UnsupportedMethod),
         };
@@ -573,6 +1288,8 @@ where
             query_str: request.query_str,
             format: request.format.unwrap_or(header_format),
             params: request.params,
+            schema: request.schema,
+            timeout: request.timeout,
         })
     }
 
@@ -593,8 +1310,10 @@ where
         }
         let statement = statements.pop().unwrap();
 
+        // The configured default database is only used if neither the parameters nor the
+        // query provide one:
         let database = match (database, statement.resolve_dbrp()) {
-            (None, None) => None,
+            (None, None) => self.default_db.clone(),
             (None, Some(db)) | (Some(db), None) => Some(db),
             (Some(p), Some(q)) => {
                 if p == q {
@@ -636,6 +1355,60 @@ where
     }
 }
 
+/// A non-secret identifier for an auth token, derived from a keyed hash of the token
+///
+/// This is safe to log or expose in metrics, as neither the token nor the hash of it that
+/// the server is configured with can be recovered from, or compared against, it. The key
+/// is generated when the process starts, so ids are only stable for the life of a process.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TokenId(String);
+
+/// The key that [`TokenId`]s are derived with
+static TOKEN_ID_KEY: OnceLock<[u8; 32]> = OnceLock::new();
+
+impl TokenId {
+    pub(crate) fn new(token: &[u8]) -> Self {
+        let key = TOKEN_ID_KEY.get_or_init(rand::random);
+        let hash = Sha512::new()
+            .chain_update(key)
+            .chain_update(token)
+            .finalize();
+        Self(hex::encode(&hash[..8]))
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Request count and latency metrics, attributed to the token used to authorize each request
+#[derive(Debug)]
+struct TokenMetrics {
+    requests: Metric<U64Counter>,
+    latency: Metric<DurationHistogram>,
+}
+
+impl TokenMetrics {
+    fn new(registry: &metric::Registry) -> Self {
+        Self {
+            requests: registry.register_metric(
+                "influxdb3_http_requests_by_token",
+                "number of authorized HTTP requests, by the id of the token used",
+            ),
+            latency: registry.register_metric(
+                "influxdb3_http_request_duration_by_token",
+                "latency of authorized HTTP requests, by the id of the token used",
+            ),
+        }
+    }
+
+    fn record(&self, token_id: TokenId, duration: Duration) {
+        let attributes = Attributes::from([("token_id", Cow::from(token_id.0))]);
+        self.requests.recorder(attributes.clone()).inc(1);
+        self.latency.recorder(attributes).record(duration);
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct V1AuthParameters {
     #[serde(rename = "p")]
@@ -659,20 +1432,100 @@ fn extract_v1_auth_token(req: &mut Request<Body>) -> Option<Vec<u8>> {
         .map(String::into_bytes)
 }
 
-fn validate_auth_header(header: HeaderValue) -> Result<Vec<u8>, AuthorizationError> {
+/// Remove the credentials that a request may carry, i.e., the `Authorization` header, and
+/// the value of the `p` query parameter used by v1 clients, so that they are not logged
+fn redact_credentials(req: &mut Request<Body>) {
+    req.headers_mut().remove(AUTHORIZATION);
+    let Some(query) = req.uri().query() else {
+        return;
+    };
+    if !query
+        .split('&')
+        .any(|pair| pair.split('=').next() == Some("p"))
+    {
+        return;
+    }
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split('=').next() {
+            Some("p") => "p=REDACTED",
+            _ => pair,
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut parts = req.uri().clone().into_parts();
+    parts.path_and_query = format!("{}?{query}", req.uri().path()).parse().ok();
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *req.uri_mut() = uri;
+    }
+}
+
+/// The write consistency levels that v1 clients may request with the `consistency` parameter
+///
+/// The server has a single node, so a successful write satisfies every level; the
+/// parameter is validated, and otherwise ignored.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum V1WriteConsistency {
+    Any,
+    One,
+    Quorum,
+    All,
+}
+
+fn validate_v1_consistency(req: &Request<Body>) -> Result<()> {
+    let params =
+        serde_urlencoded::from_str::<Vec<(String, String)>>(req.uri().query().unwrap_or_default())
+            .map_err(|_| Error::InvalidConsistency)?;
+    for (_, level) in params.iter().filter(|(name, _)| name == "consistency") {
+        let level: StrDeserializer<'_, serde::de::value::Error> =
+            level.as_str().into_deserializer();
+        V1WriteConsistency::deserialize(level).map_err(|_| Error::InvalidConsistency)?;
+    }
+    Ok(())
+}
+
+/// The auth scheme used by a request, for the audit log
+fn auth_scheme(req: &mut Request<Body>) -> Option<String> {
+    if extract_v1_auth_token(req).is_some() {
+        return Some("v1_password".to_string());
+    }
+    req.headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.split(' ').next())
+        .map(ToOwned::to_owned)
+}
+
+/// Move credentials given in the alternative auth header `name`, if one is configured, into
+/// the `Authorization` header, so they are handled as if they had been given there
+///
+/// The `Authorization` header takes precedence if both are provided.
+pub(crate) fn normalize_auth_header(headers: &mut HeaderMap, name: Option<&HeaderName>) {
+    let Some(name) = name else {
+        return;
+    };
+    if let Some(value) = headers.remove(name) {
+        if !headers.contains_key(AUTHORIZATION) {
+            headers.insert(AUTHORIZATION, value);
+        }
+    }
+}
+
+pub(crate) fn validate_auth_header(header: HeaderValue) -> Result<Vec<u8>, AuthorizationError> {
     // Split the header value into two parts
     let mut header = header.to_str()?.split(' ');
 
-    // Check that the header is the 'Bearer' auth scheme
-    let bearer = header.next().ok_or(AuthorizationError::MalformedRequest)?;
-    if bearer != "Bearer" {
+    // Check that the header is the 'Bearer' or 'ApiKey' auth scheme
+    let auth_scheme = header.next().ok_or(AuthorizationError::MalformedRequest)?;
+    if !matches!(auth_scheme, "Bearer" | "ApiKey") {
         return Err(AuthorizationError::MalformedRequest);
     }
 
     // Get the token that we want to hash to check the request is valid
     let token = header.next().ok_or(AuthorizationError::MalformedRequest)?;
 
-    // There should only be two parts the 'Bearer' scheme and the actual
+    // There should only be two parts the auth scheme and the actual
     // token, error otherwise
     if header.next().is_some() {
         return Err(AuthorizationError::MalformedRequest);
@@ -702,6 +1555,9 @@ fn validate_db_name(name: &str, accept_rp: bool) -> Result<(), ValidateDbNameErr
     if name.is_empty() {
         return Err(ValidateDbNameError::Empty);
     }
+    if name.len() > Catalog::DB_NAME_MAX_LENGTH {
+        return Err(ValidateDbNameError::TooLong(name.len()));
+    }
     let mut is_first_char = true;
     let mut rp_seperator_found = false;
     let mut last_char = None;
@@ -758,6 +1614,11 @@ pub enum ValidateDbNameError {
     InvalidRetentionPolicy,
     #[error("db name cannot be empty")]
     Empty,
+    #[error(
+        "db name is {0} bytes long, which exceeds the limit of {} bytes",
+        Catalog::DB_NAME_MAX_LENGTH
+    )]
+    TooLong(usize),
 }
 
 #[derive(Debug, Deserialize)]
@@ -768,15 +1629,25 @@ pub(crate) struct QueryRequest<D, F, P> {
     pub(crate) query_str: String,
     pub(crate) format: F,
     pub(crate) params: Option<P>,
+    /// Include the schema of the results in the response, only supported for the JSON format
+    #[serde(default)]
+    pub(crate) schema: bool,
+    /// How long the query may run before it is cancelled, e.g., `30s`
+    #[serde(default)]
+    pub(crate) timeout: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Copy, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum QueryFormat {
     Parquet,
     Csv,
     Pretty,
     Json,
+    #[serde(rename = "jsonl")]
+    JsonLines,
+    /// The Arrow IPC streaming format
+    Arrow,
 }
 
 impl QueryFormat {
@@ -786,6 +1657,8 @@ impl QueryFormat {
             Self::Csv => "text/csv",
             Self::Pretty => "text/plain; charset=utf-8",
             Self::Json => "application/json",
+            Self::JsonLines => "application/jsonl",
+            Self::Arrow => "application/vnd.apache.arrow.stream",
         }
     }
 
@@ -799,6 +1672,8 @@ impl QueryFormat {
             Some(b"application/vnd.apache.parquet") => Ok(Self::Parquet),
             Some(b"text/csv") => Ok(Self::Csv),
             Some(b"text/plain") => Ok(Self::Pretty),
+            Some(b"application/jsonl") => Ok(Self::JsonLines),
+            Some(b"application/vnd.apache.arrow.stream") => Ok(Self::Arrow),
             Some(b"application/json" | b"*/*") | None => Ok(Self::Json),
             Some(mime_type) => match String::from_utf8(mime_type.to_vec()) {
                 Ok(s) => Err(Error::InvalidMimeType(s)),
@@ -808,6 +1683,157 @@ impl QueryFormat {
     }
 }
 
+/// Reject writes whose content type shows that the body is clearly not line protocol
+///
+/// This is deliberately lenient: many clients send line protocol without a content type, or
+/// with a generic one, e.g., `curl --data` uses `application/x-www-form-urlencoded`, so only
+/// JSON and multipart bodies are rejected.
+fn validate_write_content_type(headers: &HeaderMap) -> Result<()> {
+    let Some(content_type) = headers.get(CONTENT_TYPE) else {
+        return Ok(());
+    };
+    let content_type = content_type.to_str()?;
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    if mime == "application/json" || mime.ends_with("+json") || mime.starts_with("multipart/") {
+        return Err(Error::UnsupportedWriteContentType(content_type.to_string()));
+    }
+    Ok(())
+}
+
+/// Convert metrics in the Prometheus text format to the OpenMetrics text format
+///
+/// The formats differ in how counters are named: an OpenMetrics counter family is named
+/// without the `_total` suffix that its samples must carry. OpenMetrics also declares the
+/// unit of a family with a `# UNIT` line, names untyped metrics `unknown`, escapes quotes in
+/// help text, does not allow blank lines or other comments, and requires the output to end
+/// with `# EOF`.
+fn prometheus_to_openmetrics(text: &str) -> String {
+    // Map the name of each counter in the input to the name of its OpenMetrics family:
+    let counters: std::collections::HashMap<&str, &str> = text
+        .lines()
+        .filter_map(|line| match parse_metrics_comment(line)? {
+            ("TYPE", name, "counter") => Some((name, name.strip_suffix("_total").unwrap_or(name))),
+            _ => None,
+        })
+        .collect();
+
+    let mut out = String::with_capacity(text.len() + 6);
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            match parse_metrics_comment(line) {
+                Some(("HELP", name, help)) => {
+                    let help = help.replace('"', "\\\"");
+                    let name = counters.get(name).copied().unwrap_or(name);
+                    out.push_str(&format!("# HELP {name} {help}\n"));
+                }
+                Some(("TYPE", name, kind)) => {
+                    let name = counters.get(name).copied().unwrap_or(name);
+                    let kind = if kind == "untyped" { "unknown" } else { kind };
+                    out.push_str(&format!("# TYPE {name} {kind}\n"));
+                    let unit = ["seconds", "bytes"].into_iter().find(|unit| {
+                        name.strip_suffix(unit)
+                            .is_some_and(|prefix| prefix.ends_with('_'))
+                    });
+                    if let Some(unit) = unit {
+                        out.push_str(&format!("# UNIT {name} {unit}\n"));
+                    }
+                }
+                _ => {}
+            }
+        } else if !line.is_empty() {
+            let end = line
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':'))
+                .unwrap_or(line.len());
+            let (name, rest) = line.split_at(end);
+            out.push_str(name);
+            // Counters whose samples are named without the `_total` suffix are given it:
+            if counters.get(name) == Some(&name) {
+                out.push_str("_total");
+            }
+            out.push_str(rest);
+            out.push('\n');
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+/// Split a comment line of the Prometheus text format into its keyword, metric name, and
+/// the remaining text, e.g., the help text of a `# HELP` line
+fn parse_metrics_comment(line: &str) -> Option<(&str, &str, &str)> {
+    let rest = line.strip_prefix('#')?.trim_start();
+    let (keyword, rest) = rest.split_once(char::is_whitespace)?;
+    let rest = rest.trim_start();
+    let (name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((keyword, name, rest.trim()))
+}
+
+/// Returns true if the request's `Accept-Encoding` header allows a gzip-compressed response
+///
+/// A `gzip` coding with a q-value of zero, or one that cannot be parsed, refuses gzip.
+fn accepts_gzip(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let is_gzip = parts.next().is_some_and(|c| c.eq_ignore_ascii_case("gzip"));
+            is_gzip
+                && parts
+                    .filter_map(|p| p.split_once('='))
+                    .filter(|(name, _)| name.trim().eq_ignore_ascii_case("q"))
+                    .all(|(_, q)| q.trim().parse::<f32>().is_ok_and(|q| q > 0.0))
+        })
+}
+
+/// Fail the body of a query response if it is still being produced at the `deadline`
+///
+/// By then the response has been sent with a success status, so the body is failed, which
+/// aborts the response, rather than ended early, which would look like a complete result to
+/// the client. Dropping the body drops the record batch stream that it is produced from,
+/// which cancels the query.
+fn body_with_deadline(body: Body, deadline: tokio::time::Instant, timeout: Duration) -> Body {
+    Body::wrap_stream(futures::stream::try_unfold(
+        (body, Box::pin(tokio::time::sleep_until(deadline))),
+        move |(mut body, mut expired)| async move {
+            tokio::select! {
+                biased;
+                _ = &mut expired => Err(Error::QueryTimeout(timeout)),
+                chunk = body.next() => match chunk {
+                    Some(chunk) => Ok(Some((chunk?, (body, expired)))),
+                    None => Ok(None),
+                },
+            }
+        },
+    ))
+}
+
+/// Build the response to a query, gzip-compressing the body if the client accepts it
+///
+/// The body is compressed as it is streamed, so that streamed formats are not buffered in
+/// memory to be compressed.
+fn query_response(format: QueryFormat, body: Body, gzip: bool) -> Result<Response<Body>> {
+    let builder = Response::builder()
+        .status(StatusCode::OK)
+        .header(CONTENT_TYPE, format.as_content_type());
+    if !gzip {
+        return builder.body(body).map_err(Into::into);
+    }
+
+    let encoder = GzipEncoder::new(StreamReader::new(body.map_err(std::io::Error::other)));
+    builder
+        .header(CONTENT_ENCODING, "gzip")
+        .body(Body::wrap_stream(ReaderStream::new(encoder)))
+        .map_err(Into::into)
+}
+
 async fn record_batch_stream_to_body(
     stream: Pin<Box<dyn RecordBatchStream + Send>>,
     format: QueryFormat,
@@ -823,6 +1849,17 @@ async fn record_batch_stream_to_body(
         Ok(Bytes::from(writer.into_inner()))
     }
 
+    fn to_json_lines(batches: Vec<RecordBatch>) -> Result<Bytes> {
+        let mut writer = arrow_json::LineDelimitedWriter::new(Vec::new());
+        for batch in batches {
+            writer.write(&batch)?;
+        }
+
+        writer.finish()?;
+
+        Ok(Bytes::from(writer.into_inner()))
+    }
+
     fn to_csv(batches: Vec<RecordBatch>) -> Result<Bytes> {
         let mut writer = arrow_csv::writer::Writer::new(Vec::new());
         for batch in batches {
@@ -851,6 +1888,43 @@ async fn record_batch_stream_to_body(
         Ok(Bytes::from(bytes))
     }
 
+    // JSON lines can be written a batch at a time, so the response is streamed as batches are
+    // produced, rather than buffering the entire result set in memory:
+    if let QueryFormat::JsonLines = format {
+        return Ok(Body::wrap_stream(
+            stream.map(|batch| to_json_lines(vec![batch?])),
+        ));
+    }
+
+    // The Arrow IPC stream is written the same way, starting with the schema, and ending
+    // with the end-of-stream marker once all batches have been written:
+    if let QueryFormat::Arrow = format {
+        let writer = StreamWriter::try_new(Vec::new(), &stream.schema())?;
+        return Ok(Body::wrap_stream(futures::stream::try_unfold(
+            (stream, Some(writer)),
+            |(mut stream, mut writer)| async move {
+                let Some(w) = writer.as_mut() else {
+                    return Ok(None);
+                };
+                let finished = match stream.try_next().await? {
+                    Some(batch) => {
+                        w.write(&batch)?;
+                        false
+                    }
+                    None => {
+                        w.finish()?;
+                        true
+                    }
+                };
+                let bytes = Bytes::from(std::mem::take(w.get_mut()));
+                if finished {
+                    writer = None;
+                }
+                Ok::<_, Error>(Some((bytes, (stream, writer))))
+            },
+        )));
+    }
+
     let batches = stream.try_collect::<Vec<RecordBatch>>().await?;
 
     match format {
@@ -858,30 +1932,122 @@ async fn record_batch_stream_to_body(
         QueryFormat::Parquet => to_parquet(batches),
         QueryFormat::Csv => to_csv(batches),
         QueryFormat::Json => to_json(batches),
+        QueryFormat::JsonLines => to_json_lines(batches),
+        QueryFormat::Arrow => unreachable!("the Arrow format is streamed above"),
     }
     .map(Body::from)
 }
 
+/// The schema of a query's results, as included in JSON responses when requested
+#[derive(Debug, Serialize)]
+struct QueryResultSchema {
+    columns: Vec<QueryResultColumn>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueryResultColumn {
+    name: String,
+    /// The Arrow data type of the column
+    #[serde(rename = "type")]
+    data_type: String,
+    /// Whether the column is a `tag`, `field`, or `timestamp`, if it comes from a table
+    influx_type: Option<&'static str>,
+}
+
+impl From<&SchemaRef> for QueryResultSchema {
+    fn from(schema: &SchemaRef) -> Self {
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| {
+                // Columns taken directly from a table carry their influx type in the
+                // field metadata, while computed columns do not:
+                let influx_type =
+                    Schema::try_from(Arc::new(ArrowSchema::new(vec![Arc::clone(field)])))
+                        .ok()
+                        .map(|s| match s.field(0).0 {
+                            InfluxColumnType::Tag => "tag",
+                            InfluxColumnType::Field(_) => "field",
+                            InfluxColumnType::Timestamp => "timestamp",
+                        });
+                QueryResultColumn {
+                    name: field.name().to_string(),
+                    data_type: field.data_type().to_string(),
+                    influx_type,
+                }
+            })
+            .collect();
+        Self { columns }
+    }
+}
+
+/// Serialize a stream of record batches as a JSON object, holding the schema of the
+/// results alongside the rows as they would be returned by the `json` format
+async fn record_batch_stream_to_json_with_schema(
+    stream: Pin<Box<dyn RecordBatchStream + Send>>,
+) -> Result<Body, Error> {
+    let schema = serde_json::to_vec(&QueryResultSchema::from(&stream.schema()))?;
+    let batches = stream.try_collect::<Vec<RecordBatch>>().await?;
+    let mut writer = arrow_json::ArrayWriter::new(Vec::new());
+    for batch in batches {
+        writer.write(&batch)?;
+    }
+    writer.finish()?;
+    let data = writer.into_inner();
+
+    let mut body = Vec::with_capacity(schema.len() + data.len() + 20);
+    body.extend_from_slice(b"{\"schema\":");
+    body.extend_from_slice(&schema);
+    body.extend_from_slice(b",\"data\":");
+    body.extend_from_slice(&data);
+    body.push(b'}');
+    Ok(Body::from(body))
+}
+
 // This is a hack around the fact that bool default is false not true
 const fn true_fn() -> bool {
     true
 }
 #[derive(Debug, Deserialize)]
 pub(crate) struct WriteParams {
-    pub(crate) db: String,
+    #[serde(default)]
+    pub(crate) db: Option<String>,
     #[serde(default = "true_fn")]
     pub(crate) accept_partial: bool,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_precision")]
     pub(crate) precision: Precision,
+    /// Respond with a JSON summary of the lines written, rather than an empty body
+    #[serde(default)]
+    pub(crate) summary: bool,
+}
+
+/// Deserialize the `precision` of a write, accepting either the full name of the unit or its
+/// abbreviation, and listing the accepted values if neither was given
+fn deserialize_precision<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Precision, D::Error> {
+    let precision = String::deserialize(deserializer)?;
+    match precision.as_str() {
+        "auto" => Ok(Precision::Auto),
+        "s" | "second" => Ok(Precision::Second),
+        "ms" | "millisecond" => Ok(Precision::Millisecond),
+        "us" | "µs" | "microsecond" => Ok(Precision::Microsecond),
+        "ns" | "nanosecond" => Ok(Precision::Nanosecond),
+        _ => Err(serde::de::Error::custom(format!(
+            "invalid precision '{precision}', expected one of: auto, ns, us, µs, ms, s, \
+            nanosecond, microsecond, millisecond, second"
+        ))),
+    }
 }
 
 impl From<iox_http::write::WriteParams> for WriteParams {
     fn from(legacy: iox_http::write::WriteParams) -> Self {
         Self {
-            db: legacy.namespace.to_string(),
+            db: Some(legacy.namespace.to_string()),
             // legacy behaviour was to not accept partial:
             accept_partial: false,
             precision: legacy.precision.into(),
+            summary: false,
         }
     }
 }
@@ -893,11 +2059,27 @@ pub(crate) async fn route_request<W: WriteBuffer, Q: QueryExecutor, T: TimeProvi
 where
     Error: From<<Q as QueryExecutor>::Error>,
 {
-    if let Err(e) = http_server.authorize_request(&mut req).await {
+    normalize_auth_header(req.headers_mut(), http_server.auth_header_name());
+    let path = req.uri().path().to_owned();
+    let scheme = auth_scheme(&mut req);
+    let auth_result = http_server.authorize_request(&mut req).await;
+    audit_auth_decision(
+        &path,
+        scheme.as_deref(),
+        req.extensions().get::<TokenId>(),
+        req.extensions().get::<ClientAddr>().map(|addr| addr.0),
+        match auth_result {
+            Ok(()) => AuthOutcome::Authorized,
+            Err(AuthorizationError::Forbidden) => AuthOutcome::Forbidden,
+            Err(_) => AuthOutcome::Unauthorized,
+        },
+    );
+    if let Err(e) = auth_result {
         match e {
             AuthorizationError::Unauthorized => {
                 return Ok(Response::builder()
                     .status(StatusCode::UNAUTHORIZED)
+                    .header(WWW_AUTHENTICATE, http_server.auth_realm.challenge())
                     .body(Body::empty())
                     .unwrap())
             }
@@ -905,7 +2087,8 @@ where
                 return Ok(Response::builder()
                     .status(StatusCode::BAD_REQUEST)
                     .body(Body::from("{\"error\":\
-                        \"Authorization header was malformed and should be in the form 'Authorization: Bearer <token>'\"\
+                        \"Authorization header was malformed and should be in the form 'Authorization: <auth-scheme> <token>', \
+                        supported auth-schemes are Bearer and ApiKey\"\
                     }"))
                     .unwrap());
             }
@@ -925,8 +2108,31 @@ where
             }
         }
     }
+    // Without an authorizer that validates tokens, any token is accepted, so only tokens
+    // that were validated are rate limited and attributed in metrics; otherwise a client
+    // could evade the limit, and add metric series without bound, by varying its token:
+    let token_id = req
+        .extensions()
+        .get::<TokenId>()
+        .cloned()
+        .filter(|_| http_server.authenticates_tokens);
+    if let (Some(limiter), Some(token_id)) = (&http_server.rate_limiter, &token_id) {
+        if let Err(retry_after) = limiter.try_acquire(token_id) {
+            // Round up, as Retry-After is given in whole seconds:
+            let retry_after_secs =
+                retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            return Ok(Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(RETRY_AFTER, retry_after_secs.max(1))
+                .body(Body::from(
+                    "{\"error\":\"rate limit exceeded for the provided token\"}",
+                ))
+                .unwrap());
+        }
+    }
     debug!(request = ?req,"Processing request");
 
+    let start = Instant::now();
     let method = req.method().clone();
     let uri = req.uri().clone();
     let content_length = req.headers().get("content-length").cloned();
@@ -938,7 +2144,7 @@ where
                 Err(e) => return Ok(legacy_write_error_to_response(e)),
             };
 
-            http_server.write_lp_inner(params, req, true, false).await
+            http_server.write_v1(params, req).await
         }
         (Method::POST, "/api/v2/write") => {
             let params = match http_server.legacy_write_param_unifier.parse_v2(&req).await {
@@ -955,9 +2161,11 @@ where
             http_server.query_influxql(req).await
         }
         (Method::GET, "/query") => http_server.v1_query(req).await,
-        (Method::GET, "/health" | "/api/v1/health") => http_server.health(),
+        (Method::GET, "/health" | "/api/v1/health") => http_server.health(req).await,
         (Method::GET | Method::POST, "/ping") => http_server.ping(),
-        (Method::GET, "/metrics") => http_server.handle_metrics(),
+        (Method::GET, "/api/v3/capabilities") => http_server.capabilities(),
+        (Method::GET, "/api/v3/configure/table") => http_server.configure_table_schema(req),
+        (Method::GET, "/metrics") => http_server.handle_metrics(req),
         _ => {
             let body = Body::from("not found");
             Ok(Response::builder()
@@ -967,6 +2175,10 @@ where
         }
     };
 
+    if let Some(token_id) = token_id {
+        http_server.token_metrics.record(token_id, start.elapsed());
+    }
+
     // TODO: Move logging to TraceLayer
     match response {
         Ok(response) => {
@@ -997,6 +2209,13 @@ fn legacy_write_error_to_response(e: WriteParseError) -> Response<Body> {
 
 #[cfg(test)]
 mod tests {
+    use hyper::header::ACCEPT_ENCODING;
+    use hyper::HeaderMap;
+
+    use super::accepts_gzip;
+    use super::prometheus_to_openmetrics;
+    use super::redact_credentials;
+    use super::suggest_columns;
     use super::validate_db_name;
     use super::ValidateDbNameError;
 
@@ -1007,6 +2226,48 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_accepts_gzip() {
+        let accepts = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_ENCODING, value.parse().unwrap());
+            accepts_gzip(&headers)
+        };
+        assert!(accepts("gzip"));
+        assert!(accepts("deflate, GZIP"));
+        assert!(accepts("gzip;q=0.5"));
+        assert!(accepts("br;q=1.0, gzip; q=0.001"));
+        assert!(!accepts("deflate"));
+        assert!(!accepts("gzip;q=0"));
+        assert!(!accepts("gzip;q=0.0"));
+        assert!(!accepts("gzip; q=0"));
+        assert!(!accepts("gzip;Q=0.000"));
+        assert!(!accepts("gzip;q=invalid"));
+        assert!(!accepts_gzip(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_redact_credentials() {
+        let mut req = hyper::Request::get("/query?db=foo&u=me&p=secret&q=SELECT%201")
+            .header(hyper::header::AUTHORIZATION, "Token secret")
+            .body(hyper::Body::empty())
+            .unwrap();
+        redact_credentials(&mut req);
+        assert!(req.headers().get(hyper::header::AUTHORIZATION).is_none());
+        assert_eq!(
+            req.uri().to_string(),
+            "/query?db=foo&u=me&p=REDACTED&q=SELECT%201"
+        );
+        assert!(!format!("{req:?}").contains("secret"));
+
+        // Requests without credentials are left as they are:
+        let mut req = hyper::Request::get("/api/v3/query_sql?db=foo&pretty=p")
+            .body(hyper::Body::empty())
+            .unwrap();
+        redact_credentials(&mut req);
+        assert_eq!(req.uri().to_string(), "/api/v3/query_sql?db=foo&pretty=p");
+    }
+
     #[test]
     fn test_validate_db_name() {
         assert_validate_db_name!("foo/bar", false, Err(ValidateDbNameError::InvalidChar));
@@ -1025,5 +2286,87 @@ mod tests {
         assert_validate_db_name!("foo/bar/baz", false, Err(ValidateDbNameError::InvalidChar));
         assert_validate_db_name!("_foo", false, Err(ValidateDbNameError::InvalidStartChar));
         assert_validate_db_name!("", false, Err(ValidateDbNameError::Empty));
+        assert!(validate_db_name(&"a".repeat(64), false).is_ok());
+        assert!(matches!(
+            validate_db_name(&"a".repeat(65), false),
+            Err(ValidateDbNameError::TooLong(65))
+        ));
+        assert_validate_db_name!("foo\nbar", false, Err(ValidateDbNameError::InvalidChar));
+    }
+
+    #[test]
+    fn test_prometheus_to_openmetrics() {
+        let prometheus = "\
+            # HELP http_requests_total Number of requests\n\
+            # TYPE http_requests_total counter\n\
+            http_requests_total{path=\"/ping\"} 3\n\
+            \n\
+            # HELP writes Number of writes\n\
+            # TYPE writes counter\n\
+            writes 2\n\
+            # HELP buffer_size Size of the buffer\n\
+            # TYPE buffer_size gauge\n\
+            buffer_size 1024\n";
+        assert_eq!(
+            prometheus_to_openmetrics(prometheus),
+            "\
+            # HELP http_requests Number of requests\n\
+            # TYPE http_requests counter\n\
+            http_requests_total{path=\"/ping\"} 3\n\
+            # HELP writes Number of writes\n\
+            # TYPE writes counter\n\
+            writes_total 2\n\
+            # HELP buffer_size Size of the buffer\n\
+            # TYPE buffer_size gauge\n\
+            buffer_size 1024\n\
+            # EOF\n"
+        );
+    }
+
+    #[test]
+    fn test_prometheus_to_openmetrics_metadata() {
+        let prometheus = "\
+            # HELP query_duration_seconds Time taken by \"SELECT\" queries\n\
+            # TYPE query_duration_seconds histogram\n\
+            query_duration_seconds_bucket{le=\"0.5\"} 1\n\
+            query_duration_seconds_bucket{le=\"+Inf\"} 2\n\
+            query_duration_seconds_sum 1.5\n\
+            query_duration_seconds_count 2\n\
+            # HELP written_bytes_total Bytes written\n\
+            # TYPE written_bytes_total counter\n\
+            written_bytes_total 512\n\
+            # A comment that is not metadata\n\
+            # TYPE queued untyped\n\
+            queued\t4\n";
+        assert_eq!(
+            prometheus_to_openmetrics(prometheus),
+            "\
+            # HELP query_duration_seconds Time taken by \\\"SELECT\\\" queries\n\
+            # TYPE query_duration_seconds histogram\n\
+            # UNIT query_duration_seconds seconds\n\
+            query_duration_seconds_bucket{le=\"0.5\"} 1\n\
+            query_duration_seconds_bucket{le=\"+Inf\"} 2\n\
+            query_duration_seconds_sum 1.5\n\
+            query_duration_seconds_count 2\n\
+            # HELP written_bytes Bytes written\n\
+            # TYPE written_bytes counter\n\
+            # UNIT written_bytes bytes\n\
+            written_bytes_total 512\n\
+            # TYPE queued unknown\n\
+            queued\t4\n\
+            # EOF\n"
+        );
+    }
+
+    #[test]
+    fn test_suggest_columns() {
+        let columns = ["host", "region", "usage", "usage_user", "time"];
+        assert_eq!(suggest_columns("usge", columns.into_iter()), ["usage"]);
+        assert_eq!(suggest_columns("Hst", columns.into_iter()), ["host"]);
+        assert_eq!(
+            suggest_columns("usage_usr", columns.into_iter()),
+            ["usage_user"]
+        );
+        assert!(suggest_columns("xyz", columns.into_iter()).is_empty());
     }
 }
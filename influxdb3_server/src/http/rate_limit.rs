@@ -0,0 +1,145 @@
+//! Per-token rate limiting for the HTTP API
+
+use std::{
+    collections::HashMap,
+    num::NonZeroU32,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use super::TokenId;
+
+/// Limits the rate of requests made with each token
+///
+/// Each token gets its own token bucket, which holds up to one second's worth of
+/// requests and is refilled continuously at the configured rate, so that a burst
+/// of requests from one token does not affect requests made with other tokens.
+///
+/// A bucket that has not been used for a second is full, which is no different from
+/// not having a bucket at all, so such buckets are evicted to keep the number of buckets
+/// bounded by the number of tokens in use.
+#[derive(Debug)]
+pub(crate) struct TokenRateLimiter {
+    requests_per_second: f64,
+    buckets: Mutex<Buckets>,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    by_token: HashMap<TokenId, TokenBucket>,
+    last_eviction: Instant,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    available: f64,
+    last_refill: Instant,
+}
+
+/// How long a bucket takes to refill completely
+const REFILL_PERIOD: Duration = Duration::from_secs(1);
+
+impl TokenRateLimiter {
+    pub(crate) fn new(requests_per_second: NonZeroU32) -> Self {
+        Self {
+            requests_per_second: requests_per_second.get() as f64,
+            buckets: Mutex::new(Buckets {
+                by_token: HashMap::new(),
+                last_eviction: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a permit for a request made with the given token
+    ///
+    /// If the token has exceeded its rate, this returns how long the caller should wait
+    /// before a permit will be available.
+    pub(crate) fn try_acquire(&self, token_id: &TokenId) -> Result<(), Duration> {
+        self.try_acquire_at(token_id, Instant::now())
+    }
+
+    fn try_acquire_at(&self, token_id: &TokenId, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock();
+        if now.saturating_duration_since(buckets.last_eviction) >= REFILL_PERIOD {
+            buckets.by_token.retain(|_, bucket| {
+                now.saturating_duration_since(bucket.last_refill) < REFILL_PERIOD
+            });
+            buckets.last_eviction = now;
+        }
+
+        let bucket = buckets
+            .by_token
+            .entry(token_id.clone())
+            .or_insert_with(|| TokenBucket {
+                available: self.requests_per_second,
+                last_refill: now,
+            });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.available = (bucket.available + elapsed.as_secs_f64() * self.requests_per_second)
+            .min(self.requests_per_second);
+        bucket.last_refill = now;
+
+        if bucket.available >= 1.0 {
+            bucket.available -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.available) / self.requests_per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        num::NonZeroU32,
+        time::{Duration, Instant},
+    };
+
+    use super::{TokenId, TokenRateLimiter};
+
+    #[test]
+    fn rate_limit_per_token() {
+        let limiter = TokenRateLimiter::new(NonZeroU32::new(2).unwrap());
+        let a = TokenId::new(b"token-a");
+        let b = TokenId::new(b"token-b");
+        let start = Instant::now();
+
+        // The bucket starts full, with one second's worth of requests:
+        assert!(limiter.try_acquire_at(&a, start).is_ok());
+        assert!(limiter.try_acquire_at(&a, start).is_ok());
+        let retry_after = limiter.try_acquire_at(&a, start).unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+
+        // Other tokens are not penalized:
+        assert!(limiter.try_acquire_at(&b, start).is_ok());
+
+        // The bucket refills over time:
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.try_acquire_at(&a, later).is_ok());
+        assert!(limiter.try_acquire_at(&a, later).is_err());
+    }
+
+    #[test]
+    fn rate_limit_evicts_idle_buckets() {
+        let limiter = TokenRateLimiter::new(NonZeroU32::new(1).unwrap());
+        let start = Instant::now();
+        for i in 0..100 {
+            let token_id = TokenId::new(format!("token-{i}").as_bytes());
+            assert!(limiter.try_acquire_at(&token_id, start).is_ok());
+        }
+        assert_eq!(limiter.buckets.lock().by_token.len(), 100);
+
+        // Once idle for long enough to have refilled, the buckets are evicted:
+        let a = TokenId::new(b"token-a");
+        let later = start + Duration::from_secs(2);
+        assert!(limiter.try_acquire_at(&a, later).is_ok());
+        assert_eq!(limiter.buckets.lock().by_token.len(), 1);
+
+        // And an evicted bucket starts out full again, so the limit is still applied:
+        assert!(limiter.try_acquire_at(&a, later).is_err());
+    }
+}
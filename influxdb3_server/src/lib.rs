@@ -18,7 +18,7 @@ mod http;
 pub mod query_executor;
 mod service;
 
-use crate::grpc::make_flight_server;
+use crate::grpc::{make_flight_server, GrpcAuthService};
 use crate::http::route_request;
 use crate::http::HttpApi;
 use async_trait::async_trait;
@@ -178,9 +178,10 @@ where
         TRACE_SERVER_NAME,
     );
 
-    let grpc_service = trace_layer.clone().layer(make_flight_server(
-        Arc::clone(&server.http.query_executor),
-        Some(server.authorizer()),
+    let grpc_service = trace_layer.clone().layer(GrpcAuthService::new(
+        make_flight_server(Arc::clone(&server.http.query_executor), None),
+        server.authorizer(),
+        server.http.auth_header_name().cloned(),
     ));
     let rest_service = hyper::service::make_service_fn(|_| {
         let http_server = Arc::clone(&server.http);
@@ -225,9 +226,11 @@ pub async fn wait_for_signal() {
 
 #[cfg(test)]
 mod tests {
-    use crate::auth::DefaultAuthorizer;
+    use crate::auth::AllOrNothingAuthorizer;
     use crate::builder::ServerBuilder;
     use crate::serve;
+    use async_trait::async_trait;
+    use authz::{Authorizer, Permission};
     use datafusion::parquet::data_type::AsBytes;
     use hyper::{body, Body, Client, Request, Response, StatusCode};
     use influxdb3_write::persister::PersisterImpl;
@@ -237,11 +240,14 @@ mod tests {
     use object_store::DynObjectStore;
     use parquet_file::storage::{ParquetStorage, StorageId};
     use pretty_assertions::assert_eq;
+    use sha2::{Digest, Sha512};
     use std::collections::HashMap;
     use std::net::{SocketAddr, SocketAddrV4};
-    use std::num::NonZeroUsize;
+    use std::num::{NonZeroU32, NonZeroUsize};
     use std::sync::atomic::{AtomicU16, Ordering};
     use std::sync::Arc;
+    use test_helpers::tracing::TracingCapture;
+    use test_helpers::{assert_contains, assert_not_contains};
     use tokio_util::sync::CancellationToken;
 
     static NEXT_PORT: AtomicU16 = AtomicU16::new(8090);
@@ -292,13 +298,14 @@ mod tests {
             Arc::new(HashMap::new()),
             10,
             10,
+            None,
+            false,
         ));
 
         let server = ServerBuilder::new(common_state)
             .write_buffer(Arc::clone(&write_buffer))
             .query_executor(Arc::clone(&query_executor))
             .persister(Arc::clone(&persister))
-            .authorizer(Arc::new(DefaultAuthorizer))
             .time_provider(Arc::clone(&time_provider))
             .build();
         let frontend_shutdown = CancellationToken::new();
@@ -453,13 +460,14 @@ mod tests {
             Arc::new(HashMap::new()),
             10,
             10,
+            None,
+            false,
         );
 
         let server = ServerBuilder::new(common_state)
             .write_buffer(Arc::clone(&write_buffer))
             .query_executor(Arc::new(query_executor))
             .persister(persister)
-            .authorizer(Arc::new(DefaultAuthorizer))
             .time_provider(Arc::clone(&time_provider))
             .build();
         let frontend_shutdown = CancellationToken::new();
@@ -610,6 +618,42 @@ mod tests {
             }"
         );
 
+        let resp = write_lp(
+            &server,
+            "a".repeat(65),
+            "cpu,host=b val=2 155\n",
+            None,
+            true,
+            "nanosecond",
+        )
+        .await;
+
+        let status = resp.status();
+        let body =
+            String::from_utf8(body::to_bytes(resp.into_body()).await.unwrap().to_vec()).unwrap();
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            "{\
+                \"error\":\"db name is 65 bytes long, which exceeds the limit of 64 bytes\",\
+                \"data\":null\
+            }"
+        );
+
+        // A database name containing a newline, URL-encoded:
+        let resp = write_lp(
+            &server,
+            "foo%0Abar",
+            "cpu,host=b val=2 155\n",
+            None,
+            true,
+            "nanosecond",
+        )
+        .await;
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
         shutdown.cancel();
     }
 
@@ -661,13 +705,14 @@ mod tests {
             Arc::new(HashMap::new()),
             10,
             10,
+            None,
+            false,
         );
 
         let server = ServerBuilder::new(common_state)
             .write_buffer(Arc::clone(&write_buffer))
             .query_executor(Arc::new(query_executor))
             .persister(persister)
-            .authorizer(Arc::new(DefaultAuthorizer))
             .time_provider(Arc::clone(&time_provider))
             .build();
         let frontend_shutdown = CancellationToken::new();
@@ -789,6 +834,226 @@ mod tests {
         shutdown.cancel();
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn auth_audit_log() {
+        let capture = TracingCapture::new();
+        let (server, shutdown) = start_server(Arc::new(AllOrNothingAuthorizer::new(
+            Sha512::digest(b"apiv3_token").to_vec(),
+        )))
+        .await;
+
+        let res = write_lp(
+            &server,
+            "foo",
+            "cpu,host=a val=1i 123",
+            Some("Bearer apiv3_wrong"),
+            false,
+            "nanosecond",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        let events = capture.to_string();
+        let event = events
+            .lines()
+            .find(|line| line.contains("authorization decision"))
+            .expect("an audit event was logged");
+        assert_contains!(event, "/api/v3/write_lp");
+        assert_contains!(event, "Bearer");
+        assert_contains!(event, "unauthorized");
+        assert_contains!(event, "127.0.0.1");
+        assert_not_contains!(event, "apiv3_wrong");
+
+        shutdown.cancel();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn auth_unauthorized_vs_forbidden() {
+        let (server, shutdown) = start_server(Arc::new(ScopedAuthorizer)).await;
+        let lp = "cpu,host=a val=1i 123";
+
+        // A missing or invalid token must authenticate again:
+        let res = write_lp(&server, "foo", lp, None, false, "nanosecond").await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = write_lp(
+            &server,
+            "foo",
+            lp,
+            Some("Bearer apiv3_wrong"),
+            false,
+            "nanosecond",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+        // A valid token that lacks permission is forbidden:
+        let res = write_lp(
+            &server,
+            "foo",
+            lp,
+            Some("Bearer apiv3_read_only"),
+            false,
+            "nanosecond",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+        let res = write_lp(
+            &server,
+            "foo",
+            lp,
+            Some("Bearer apiv3_token"),
+            false,
+            "nanosecond",
+        )
+        .await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        shutdown.cancel();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn auth_rate_limit_is_per_token() {
+        let (server, shutdown) =
+            start_server_with_rate_limit(Arc::new(ScopedAuthorizer), NonZeroU32::new(2)).await;
+        let lp = "cpu,host=a val=1i 123";
+
+        let mut statuses = vec![];
+        for _ in 0..10 {
+            let res = write_lp(
+                &server,
+                "foo",
+                lp,
+                Some("Bearer apiv3_token"),
+                false,
+                "nanosecond",
+            )
+            .await;
+            statuses.push(res.status());
+        }
+        assert!(
+            statuses.contains(&StatusCode::TOO_MANY_REQUESTS),
+            "the first token was not rate limited: {statuses:?}"
+        );
+
+        // Another valid token is not penalized for the first token's requests:
+        for _ in 0..2 {
+            let res = write_lp(
+                &server,
+                "foo",
+                lp,
+                Some("Bearer apiv3_other_token"),
+                false,
+                "nanosecond",
+            )
+            .await;
+            assert_eq!(res.status(), StatusCode::OK);
+        }
+
+        shutdown.cancel();
+    }
+
+    /// An [`Authorizer`] with two valid tokens, and a valid token that is not permitted to
+    /// do anything
+    #[derive(Debug)]
+    struct ScopedAuthorizer;
+
+    #[async_trait]
+    impl Authorizer for ScopedAuthorizer {
+        async fn permissions(
+            &self,
+            token: Option<Vec<u8>>,
+            perms: &[Permission],
+        ) -> Result<Vec<Permission>, authz::Error> {
+            match token.as_deref() {
+                Some(b"apiv3_token" | b"apiv3_other_token") => Ok(perms.to_vec()),
+                Some(b"apiv3_read_only") => Err(authz::Error::Forbidden),
+                Some(_) => Err(authz::Error::InvalidToken),
+                None => Err(authz::Error::NoToken),
+            }
+        }
+
+        async fn probe(&self) -> Result<(), authz::Error> {
+            Ok(())
+        }
+    }
+
+    /// Start a server using the given authorizer, returning its address and a token
+    /// to shut it down
+    async fn start_server(authorizer: Arc<dyn Authorizer>) -> (String, CancellationToken) {
+        start_server_with_rate_limit(authorizer, None).await
+    }
+
+    /// Start a server using the given authorizer and per-token rate limit, returning its
+    /// address and a token to shut it down
+    async fn start_server_with_rate_limit(
+        authorizer: Arc<dyn Authorizer>,
+        rate_limit_per_token: Option<NonZeroU32>,
+    ) -> (String, CancellationToken) {
+        let addr = get_free_port();
+        let trace_header_parser = trace_http::ctx::TraceHeaderParser::new();
+        let metrics = Arc::new(metric::Registry::new());
+        let common_state =
+            crate::CommonServerState::new(Arc::clone(&metrics), None, trace_header_parser, addr)
+                .unwrap();
+        let object_store: Arc<DynObjectStore> = Arc::new(object_store::memory::InMemory::new());
+        let parquet_store =
+            ParquetStorage::new(Arc::clone(&object_store), StorageId::from("influxdb3"));
+        let exec = Arc::new(Executor::new_with_config_and_executor(
+            ExecutorConfig {
+                target_query_partitions: NonZeroUsize::new(1).unwrap(),
+                object_stores: [&parquet_store]
+                    .into_iter()
+                    .map(|store| (store.id(), Arc::clone(store.object_store())))
+                    .collect(),
+                metric_registry: Arc::clone(&metrics),
+                mem_pool_size: usize::MAX,
+            },
+            DedicatedExecutor::new_testing(),
+        ));
+        let persister = Arc::new(PersisterImpl::new(Arc::clone(&object_store)));
+        let time_provider = Arc::new(MockProvider::new(Time::from_timestamp_nanos(0)));
+
+        let write_buffer = Arc::new(
+            influxdb3_write::write_buffer::WriteBufferImpl::new(
+                Arc::clone(&persister),
+                None::<Arc<influxdb3_write::wal::WalImpl>>,
+                Arc::clone(&time_provider),
+                SegmentDuration::new_5m(),
+                Arc::clone(&exec),
+                10000,
+            )
+            .await
+            .unwrap(),
+        );
+        let query_executor = Arc::new(crate::query_executor::QueryExecutorImpl::new(
+            write_buffer.catalog(),
+            Arc::clone(&write_buffer),
+            Arc::clone(&exec),
+            Arc::clone(&metrics),
+            Arc::new(HashMap::new()),
+            10,
+            10,
+            None,
+            false,
+        ));
+
+        let server = ServerBuilder::new(common_state)
+            .write_buffer(Arc::clone(&write_buffer))
+            .query_executor(Arc::clone(&query_executor))
+            .persister(Arc::clone(&persister))
+            .authorizer(authorizer)
+            .rate_limit_per_token(rate_limit_per_token)
+            .time_provider(Arc::clone(&time_provider))
+            .build();
+        let frontend_shutdown = CancellationToken::new();
+        let shutdown = frontend_shutdown.clone();
+
+        tokio::spawn(async move { serve(server, frontend_shutdown).await });
+
+        (format!("http://{}", addr), shutdown)
+    }
+
     pub(crate) async fn write_lp(
         server: impl Into<String> + Send,
         database: impl Into<String> + Send,
@@ -13,11 +13,13 @@ use datafusion::catalog::schema::SchemaProvider;
 use datafusion::catalog::CatalogProvider;
 use datafusion::common::arrow::array::StringArray;
 use datafusion::common::arrow::datatypes::{DataType, Field, Schema as DatafusionSchema};
+use datafusion::common::ScalarValue;
 use datafusion::datasource::{TableProvider, TableType};
 use datafusion::error::DataFusionError;
 use datafusion::execution::context::SessionState;
 use datafusion::execution::SendableRecordBatchStream;
-use datafusion::logical_expr::TableProviderFilterPushDown;
+use datafusion::logical_expr::utils::split_conjunction;
+use datafusion::logical_expr::{BinaryExpr, Operator, TableProviderFilterPushDown};
 use datafusion::physical_plan::ExecutionPlan;
 use datafusion::prelude::Expr;
 use datafusion_util::config::DEFAULT_SCHEMA;
@@ -40,10 +42,11 @@ use iox_query_params::StatementParams;
 use iox_system_tables::{IoxSystemTable, SystemTableProvider};
 use metric::Registry;
 use observability_deps::tracing::{debug, info};
-use schema::Schema;
+use schema::{InfluxColumnType, Schema, TIME_COLUMN_NAME};
 use std::any::Any;
 use std::collections::HashMap;
 use std::fmt::Debug;
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use trace::ctx::SpanContext;
 use trace::span::{Span, SpanExt, SpanRecorder};
@@ -60,9 +63,12 @@ pub struct QueryExecutorImpl<W> {
     datafusion_config: Arc<HashMap<String, String>>,
     query_execution_semaphore: Arc<InstrumentedAsyncSemaphore>,
     query_log: Arc<QueryLog>,
+    max_chunks_per_query: Option<NonZeroUsize>,
+    canonical_column_order: bool,
 }
 
 impl<W: WriteBuffer> QueryExecutorImpl<W> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         catalog: Arc<Catalog>,
         write_buffer: Arc<W>,
@@ -71,6 +77,8 @@ impl<W: WriteBuffer> QueryExecutorImpl<W> {
         datafusion_config: Arc<HashMap<String, String>>,
         concurrent_query_limit: usize,
         query_log_size: usize,
+        max_chunks_per_query: Option<NonZeroUsize>,
+        canonical_column_order: bool,
     ) -> Self {
         let semaphore_metrics = Arc::new(AsyncSemaphoreMetrics::new(
             &metrics,
@@ -89,6 +97,8 @@ impl<W: WriteBuffer> QueryExecutorImpl<W> {
             datafusion_config,
             query_execution_semaphore,
             query_log,
+            max_chunks_per_query,
+            canonical_column_order,
         }
     }
 }
@@ -289,6 +299,15 @@ pub enum Error {
     DatabasesToRecordBatch(#[source] ArrowError),
     #[error("unable to compose record batches from retention policies: {0}")]
     RetentionPoliciesToRecordBatch(#[source] ArrowError),
+    #[error(
+        "query would scan {chunks} chunks of table {table_name}, \
+        which exceeds the limit of {limit} chunks per query"
+    )]
+    ChunkLimit {
+        table_name: String,
+        chunks: usize,
+        limit: usize,
+    },
 }
 
 // This implementation is for the Flight service
@@ -315,6 +334,8 @@ impl<W: WriteBuffer> QueryDatabase for QueryExecutorImpl<W> {
             Arc::clone(&self.exec),
             Arc::clone(&self.datafusion_config),
             Arc::clone(&self.query_log),
+            self.max_chunks_per_query,
+            self.canonical_column_order,
         ))))
     }
 
@@ -338,6 +359,8 @@ pub struct Database<B> {
     datafusion_config: Arc<HashMap<String, String>>,
     query_log: Arc<QueryLog>,
     system_schema_provider: Arc<SystemSchemaProvider>,
+    max_chunks_per_query: Option<NonZeroUsize>,
+    canonical_column_order: bool,
 }
 
 impl<B: WriteBuffer> Database<B> {
@@ -347,6 +370,8 @@ impl<B: WriteBuffer> Database<B> {
         exec: Arc<Executor>,
         datafusion_config: Arc<HashMap<String, String>>,
         query_log: Arc<QueryLog>,
+        max_chunks_per_query: Option<NonZeroUsize>,
+        canonical_column_order: bool,
     ) -> Self {
         let system_schema_provider = Arc::new(SystemSchemaProvider::new(
             write_buffer.catalog(),
@@ -359,6 +384,8 @@ impl<B: WriteBuffer> Database<B> {
             datafusion_config,
             query_log,
             system_schema_provider,
+            max_chunks_per_query,
+            canonical_column_order,
         }
     }
 
@@ -370,16 +397,32 @@ impl<B: WriteBuffer> Database<B> {
             datafusion_config: Arc::clone(&db.datafusion_config),
             query_log: Arc::clone(&db.query_log),
             system_schema_provider: Arc::clone(&db.system_schema_provider),
+            max_chunks_per_query: db.max_chunks_per_query,
+            canonical_column_order: db.canonical_column_order,
         }
     }
 
     async fn query_table(&self, table_name: &str) -> Option<Arc<QueryTable<B>>> {
         self.db_schema.get_table_schema(table_name).map(|schema| {
+            let (schema, catalog_indexes) = if self.canonical_column_order {
+                let indexes = canonical_column_order(schema);
+                let arrow_schema = schema
+                    .as_arrow()
+                    .project(&indexes)
+                    .expect("canonical column indexes are in bounds");
+                let schema = Schema::try_from(Arc::new(arrow_schema))
+                    .expect("reordering columns produces a valid schema");
+                (schema, Some(indexes))
+            } else {
+                (schema.clone(), None)
+            };
             Arc::new(QueryTable {
                 db_schema: Arc::clone(&self.db_schema),
                 name: table_name.into(),
-                schema: schema.clone(),
+                schema,
+                catalog_indexes,
                 write_buffer: Arc::clone(&self.write_buffer),
+                max_chunks: self.max_chunks_per_query,
             })
         })
     }
@@ -491,7 +534,11 @@ pub struct QueryTable<B> {
     db_schema: Arc<DatabaseSchema>,
     name: Arc<str>,
     schema: Schema,
+    /// The index in the catalog's table schema of each column of `schema`, if its columns
+    /// have been reordered
+    catalog_indexes: Option<Vec<usize>>,
     write_buffer: Arc<B>,
+    max_chunks: Option<NonZeroUsize>,
 }
 
 impl<B: WriteBuffer> QueryTable<B> {
@@ -502,13 +549,118 @@ impl<B: WriteBuffer> QueryTable<B> {
         filters: &[Expr],
         _limit: Option<usize>,
     ) -> Result<Vec<Arc<dyn QueryChunk>>, DataFusionError> {
-        self.write_buffer.get_table_chunks(
+        // The write buffer projects columns of the catalog's table schema:
+        let projection = match (projection, &self.catalog_indexes) {
+            (Some(projection), Some(indexes)) => {
+                Some(projection.iter().map(|i| indexes[*i]).collect())
+            }
+            (projection, _) => projection.cloned(),
+        };
+        let chunks = self.write_buffer.get_table_chunks(
             &self.db_schema.name,
             self.name.as_ref(),
             filters,
-            projection,
+            projection.as_ref(),
             ctx,
-        )
+        )?;
+        let Some(limit) = self.max_chunks else {
+            return Ok(chunks);
+        };
+        // Chunks are not pruned by the time range of the query when they are fetched, so
+        // drop those that cannot contain matching rows before they count towards the limit.
+        // The time range of a chunk covers at least the rows in it, so no chunk that holds
+        // matching rows is dropped:
+        let (min_time, max_time) = filter_time_range(filters);
+        let chunks: Vec<_> = chunks
+            .into_iter()
+            .filter(|chunk| match chunk_time_range(chunk.as_ref()) {
+                Some((chunk_min, chunk_max)) => chunk_min <= max_time && chunk_max >= min_time,
+                None => true,
+            })
+            .collect();
+        // Each chunk is either a buffered segment or a persisted file of the table:
+        if chunks.len() > limit.get() {
+            return Err(DataFusionError::External(Box::new(Error::ChunkLimit {
+                table_name: self.name.to_string(),
+                chunks: chunks.len(),
+                limit: limit.get(),
+            })));
+        }
+        Ok(chunks)
+    }
+}
+
+/// The indexes of the columns of `schema` in canonical order: tags, then fields, each sorted
+/// by name, then `time`
+fn canonical_column_order(schema: &Schema) -> Vec<usize> {
+    let mut columns = schema
+        .iter()
+        .enumerate()
+        .map(|(index, (column_type, field))| {
+            let rank = match column_type {
+                InfluxColumnType::Tag => 0,
+                InfluxColumnType::Field(_) => 1,
+                InfluxColumnType::Timestamp => 2,
+            };
+            (rank, field.name(), index)
+        })
+        .collect::<Vec<_>>();
+    columns.sort();
+    columns.into_iter().map(|(_, _, index)| index).collect()
+}
+
+/// The range of times, in nanoseconds, that rows must fall within to match all of the given
+/// filters
+///
+/// Only comparisons between the `time` column and timestamp literals are considered, so the
+/// range may be wider than the one the filters match, but never narrower.
+fn filter_time_range(filters: &[Expr]) -> (i64, i64) {
+    let mut range = (i64::MIN, i64::MAX);
+    for filter in filters.iter().flat_map(split_conjunction) {
+        let Expr::BinaryExpr(BinaryExpr { left, op, right }) = filter else {
+            continue;
+        };
+        let (op, value) = match (left.as_ref(), right.as_ref()) {
+            (Expr::Column(column), Expr::Literal(value)) if column.name == TIME_COLUMN_NAME => {
+                (*op, value)
+            }
+            (Expr::Literal(value), Expr::Column(column)) if column.name == TIME_COLUMN_NAME => {
+                match op.swap() {
+                    Some(op) => (op, value),
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        let ScalarValue::TimestampNanosecond(Some(time), _) = value else {
+            continue;
+        };
+        match op {
+            Operator::Eq => range = (range.0.max(*time), range.1.min(*time)),
+            Operator::Gt => range.0 = range.0.max(time.saturating_add(1)),
+            Operator::GtEq => range.0 = range.0.max(*time),
+            Operator::Lt => range.1 = range.1.min(time.saturating_sub(1)),
+            Operator::LtEq => range.1 = range.1.min(*time),
+            _ => {}
+        }
+    }
+    range
+}
+
+/// The range of times, in nanoseconds, of the rows in the given chunk, if it is known
+fn chunk_time_range(chunk: &dyn QueryChunk) -> Option<(i64, i64)> {
+    let index = chunk.schema().find_index_of(TIME_COLUMN_NAME)?;
+    let stats = chunk.stats();
+    let column_stats = stats.column_statistics.get(index)?;
+    match (
+        column_stats.min_value.get_value()?,
+        column_stats.max_value.get_value()?,
+    ) {
+        (
+            ScalarValue::TimestampNanosecond(Some(min), _),
+            ScalarValue::TimestampNanosecond(Some(max), _),
+        ) => Some((*min, *max)),
+        _ => None,
     }
 }
 
@@ -13,10 +13,12 @@
 //! [make-svc-fn]: https://docs.rs/hyper/0.14.28/src/hyper/service/make.rs.html#149-151
 //! [hyper-server]: https://docs.rs/hyper/0.14.28/hyper/server/struct.Server.html
 //! [article]: https://www.fpcomplete.com/blog/axum-hyper-tonic-tower-part1/
+use std::net::SocketAddr;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
 use futures::Future;
+use hyper::server::conn::AddrStream;
 use hyper::HeaderMap;
 use hyper::{body::HttpBody, Body, Request, Response};
 use pin_project_lite::pin_project;
@@ -32,6 +34,23 @@ pub(crate) fn hybrid<MakeRest, Grpc>(
     HybridMakeService { make_rest, grpc }
 }
 
+/// The address of the client that made a request
+///
+/// This is added to the extensions of every request served by the [`HybridService`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ClientAddr(pub(crate) SocketAddr);
+
+/// Connection info that provides the address of the connected client
+pub(crate) trait ClientConnInfo {
+    fn client_addr(&self) -> SocketAddr;
+}
+
+impl ClientConnInfo for &AddrStream {
+    fn client_addr(&self) -> SocketAddr {
+        AddrStream::remote_addr(self)
+    }
+}
+
 /// A hybrid of a "make service", i.e., a service that accepts connection info and returns
 /// a service that will serve a request over that connection as its output, and a gRPC service
 ///
@@ -46,6 +65,7 @@ pub struct HybridMakeService<MakeRest, Grpc> {
 
 impl<ConnInfo, MakeRest, Grpc> Service<ConnInfo> for HybridMakeService<MakeRest, Grpc>
 where
+    ConnInfo: ClientConnInfo,
     MakeRest: Service<ConnInfo>,
     Grpc: Clone,
 {
@@ -59,6 +79,7 @@ where
 
     fn call(&mut self, conn_info: ConnInfo) -> Self::Future {
         HybridMakeServiceFuture {
+            client_addr: conn_info.client_addr(),
             rest_future: self.make_rest.call(conn_info),
             grpc: Some(self.grpc.clone()),
         }
@@ -71,6 +92,7 @@ pin_project! {
         #[pin]
         rest_future: RestFuture,
         grpc: Option<Grpc>,
+        client_addr: SocketAddr,
     }
 }
 
@@ -86,6 +108,7 @@ where
             Poll::Ready(Ok(rest)) => Poll::Ready(Ok(HybridService {
                 rest,
                 grpc: this.grpc.take().expect("future polled after execution"),
+                client_addr: *this.client_addr,
             })),
             Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
             Poll::Pending => Poll::Pending,
@@ -97,6 +120,7 @@ where
 pub struct HybridService<Rest, Grpc> {
     rest: Rest,
     grpc: Grpc,
+    client_addr: SocketAddr,
 }
 
 impl<Rest, Grpc, RestBody, GrpcBody> Service<Request<Body>> for HybridService<Rest, Grpc>
@@ -125,7 +149,8 @@ where
     /// When calling the service, gRPC is served if the HTTP request version is HTTP/2
     /// and if the Content-Type is "application/grpc"; otherwise, the request is served
     /// as a REST request
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
+        req.extensions_mut().insert(ClientAddr(self.client_addr));
         match (
             req.version(),
             req.headers().get(hyper::header::CONTENT_TYPE),
@@ -35,6 +35,12 @@ pub enum Error {
     )]
     TooManyDbs,
 
+    #[error(
+        "database name is {0} bytes long, which exceeds the limit of {} bytes",
+        Catalog::DB_NAME_MAX_LENGTH
+    )]
+    DbNameTooLong(usize),
+
     #[error("last cache size must be from 1 to 10")]
     InvalidLastCacheSize,
 }
@@ -76,6 +82,8 @@ impl Catalog {
     pub(crate) const NUM_COLUMNS_PER_TABLE_LIMIT: usize = 500;
     /// Limit for the number of tables across all DBs that InfluxDB Edge can have
     pub(crate) const NUM_TABLES_LIMIT: usize = 2000;
+    /// Limit for the length, in bytes, of a database name
+    pub const DB_NAME_MAX_LENGTH: usize = 64;
 
     pub fn new() -> Self {
         Self {
@@ -147,6 +155,10 @@ impl Catalog {
                     return Err(Error::TooManyDbs);
                 }
 
+                if db_name.len() > Self::DB_NAME_MAX_LENGTH {
+                    return Err(Error::DbNameTooLong(db_name.len()));
+                }
+
                 info!("return new db {}", db_name);
                 let db = Arc::new(DatabaseSchema::new(db_name));
                 inner.databases.insert(db.name.clone(), Arc::clone(&db));
@@ -99,6 +99,13 @@ pub trait Bufferer: Debug + Send + Sync + 'static {
 
     /// Returns the catalog
     fn catalog(&self) -> Arc<catalog::Catalog>;
+
+    /// Returns the size, in bytes, of the data held in the open segments of the buffer
+    fn buffer_size_bytes(&self) -> usize;
+
+    /// Returns the size, in bytes, that the buffer is limited to, beyond which data is
+    /// persisted early to free memory
+    fn buffer_limit_bytes(&self) -> usize;
 }
 
 /// A segment in the buffer that corresponds to a single WAL segment file. It contains a catalog with any updates
@@ -465,6 +472,10 @@ pub struct ParquetWriteOp {
 pub struct WriteLineError {
     pub original_line: String,
     pub line_number: usize,
+    /// The 1-based byte column in `original_line` at which parsing failed, if the parser
+    /// reported where it stopped
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
     pub error_message: String,
 }
 
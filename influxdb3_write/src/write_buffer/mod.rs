@@ -112,6 +112,7 @@ pub struct WriteBufferImpl<W, T> {
     shutdown_segment_persist_tx: watch::Sender<()>,
     #[allow(dead_code)]
     buffer_check_handle: Mutex<tokio::task::JoinHandle<()>>,
+    buffer_mem_limit_mb: usize,
 }
 
 impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
@@ -193,6 +194,7 @@ impl<W: Wal, T: TimeProvider> WriteBufferImpl<W, T> {
             shutdown_segment_persist_tx,
             buffer_check_handle: Mutex::new(buffer_check_handle),
             persisted_files,
+            buffer_mem_limit_mb,
         })
     }
 
@@ -493,6 +495,19 @@ impl<W: Wal, T: TimeProvider> Bufferer for WriteBufferImpl<W, T> {
     fn catalog(&self) -> Arc<Catalog> {
         self.catalog()
     }
+
+    fn buffer_size_bytes(&self) -> usize {
+        self.segment_state
+            .read()
+            .open_segments_sizes()
+            .iter()
+            .map(|s| s.size())
+            .sum()
+    }
+
+    fn buffer_limit_bytes(&self) -> usize {
+        self.buffer_mem_limit_mb * 1024 * 1024
+    }
 }
 
 impl<W: Wal, T: TimeProvider> ChunkContainer for WriteBufferImpl<W, T> {
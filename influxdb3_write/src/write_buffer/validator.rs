@@ -1,7 +1,7 @@
 use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use data_types::NamespaceName;
-use influxdb_line_protocol::{parse_lines, v3, FieldValue, ParsedLine};
+use influxdb_line_protocol::{parse_lines, split_lines, v3, FieldValue, ParsedLine};
 use iox_time::Time;
 use schema::{InfluxColumnType, TIME_COLUMN_NAME};
 
@@ -70,18 +70,22 @@ impl WriteValidator<WithCatalog> {
         accept_partial: bool,
     ) -> Result<WriteValidator<LinesParsed<'_, v3::ParsedLine<'_>>>> {
         let mut errors = vec![];
-        let mut lp_lines = lp.lines().peekable();
         let mut lines = vec![];
         let mut schema = Cow::Borrowed(self.state.db_schema.as_ref());
 
-        for (line_idx, maybe_line) in v3::parse_lines(lp).enumerate() {
+        for (line_number, raw_line) in numbered_lines(lp) {
+            // Blank lines and comments do not produce a parsed line:
+            let Some(maybe_line) = v3::parse_lines(raw_line).next() else {
+                continue;
+            };
             let line = match maybe_line
                 .map_err(|e| WriteLineError {
-                    original_line: lp_lines.next().unwrap().to_string(),
-                    line_number: line_idx + 1,
+                    original_line: raw_line.to_string(),
+                    line_number,
+                    column: parse_error_column(raw_line, &e),
                     error_message: e.to_string(),
                 })
-                .and_then(|l| validate_v3_line(&mut schema, line_idx, l, lp_lines.peek().unwrap()))
+                .and_then(|l| validate_v3_line(&mut schema, line_number, l, raw_line))
             {
                 Ok(line) => line,
                 Err(e) => {
@@ -94,7 +98,7 @@ impl WriteValidator<WithCatalog> {
                 }
             };
 
-            lines.push((line, lp_lines.next().unwrap()));
+            lines.push((line, raw_line));
         }
 
         if let Cow::Owned(schema) = schema {
@@ -128,20 +132,22 @@ impl WriteValidator<WithCatalog> {
         accept_partial: bool,
     ) -> Result<WriteValidator<LinesParsed<'_, ParsedLine<'_>>>> {
         let mut errors = vec![];
-        let mut lp_lines = lp.lines();
         let mut lines = vec![];
         let mut schema = Cow::Borrowed(self.state.db_schema.as_ref());
 
-        for (line_idx, maybe_line) in parse_lines(lp).enumerate() {
+        for (line_number, raw_line) in numbered_lines(lp) {
+            // Blank lines and comments do not produce a parsed line:
+            let Some(maybe_line) = parse_lines(raw_line).next() else {
+                continue;
+            };
             let line = match maybe_line
                 .map_err(|e| WriteLineError {
-                    // This unwrap is fine because we're moving line by line
-                    // alongside the output from parse_lines
-                    original_line: lp_lines.next().unwrap().to_string(),
-                    line_number: line_idx + 1,
+                    original_line: raw_line.to_string(),
+                    line_number,
+                    column: parse_error_column(raw_line, &e),
                     error_message: e.to_string(),
                 })
-                .and_then(|l| validate_v1_line(&mut schema, line_idx, l))
+                .and_then(|l| validate_v1_line(&mut schema, line_number, l, raw_line))
             {
                 Ok(line) => line,
                 Err(e) => {
@@ -153,9 +159,7 @@ impl WriteValidator<WithCatalog> {
                     continue;
                 }
             };
-            // This unwrap is fine because we're moving line by line
-            // alongside the output from parse_lines
-            lines.push((line, lp_lines.next().unwrap()));
+            lines.push((line, raw_line));
         }
 
         // All lines are parsed and validated, so all steps after this
@@ -177,6 +181,34 @@ impl WriteValidator<WithCatalog> {
     }
 }
 
+/// Split `lp` into lines the way the line protocol parsers do, along with the 1-based
+/// number of the line in `lp` that each one starts on
+///
+/// The line number is derived from the byte offset of the line within `lp`, so that newlines
+/// which do not end a line, e.g., within a quoted string field value, are still counted.
+fn numbered_lines(lp: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut line_number = 1;
+    let mut counted_to = 0;
+    split_lines(lp).map(move |line| {
+        let offset = line.as_ptr() as usize - lp.as_ptr() as usize;
+        line_number += lp[counted_to..offset].matches('\n').count();
+        counted_to = offset;
+        (line_number, line.trim_end_matches(['\r', '\n']))
+    })
+}
+
+/// Get the 1-based byte column at which `raw_line` could not be parsed
+///
+/// Only a line that was parsed up to some trailing content reports where parsing stopped.
+fn parse_error_column(raw_line: &str, error: &influxdb_line_protocol::Error) -> Option<usize> {
+    match error {
+        influxdb_line_protocol::Error::CannotParseEntireLine { trailing_content } => raw_line
+            .strip_suffix(trailing_content.as_str())
+            .map(|parsed| parsed.len() + 1),
+        _ => None,
+    }
+}
+
 /// Validate an individual line of v3 line protocol and update the database
 /// schema
 ///
@@ -198,6 +230,7 @@ fn validate_v3_line<'a>(
             return Err(WriteLineError {
                 original_line: raw_line.to_string(),
                 line_number,
+                column: None,
                 error_message: "received v3 write protocol for a table that uses the v1 data model"
                     .to_string(),
             });
@@ -210,6 +243,7 @@ fn validate_v3_line<'a>(
                     return Err(WriteLineError {
                         original_line: raw_line.to_string(),
                         line_number,
+                        column: None,
                         error_message: format!(
                             "write to table {table_name} had the incorrect series key, \
                             expected: [{expected}], received: [{received}]",
@@ -225,6 +259,7 @@ fn validate_v3_line<'a>(
                     return Err(WriteLineError {
                         original_line: raw_line.to_string(),
                         line_number,
+                        column: None,
                         error_message: format!(
                             "write to table {table_name} was missing a series key, the series key \
                             contains [{key_members}]",
@@ -250,7 +285,8 @@ fn validate_v3_line<'a>(
                     let field_name = field_name.to_string();
                     return Err(WriteLineError {
                         original_line: raw_line.to_string(),
-                        line_number: line_number + 1,
+                        line_number,
+                        column: None,
                         error_message: format!(
                         "invalid field value in line protocol for field '{field_name}' on line \
                         {line_number}: expected type {expected}, but got {got}",
@@ -313,13 +349,15 @@ fn validate_v1_line<'a>(
     db_schema: &mut Cow<'_, DatabaseSchema>,
     line_number: usize,
     line: ParsedLine<'a>,
+    raw_line: &str,
 ) -> Result<ParsedLine<'a>, WriteLineError> {
     let table_name = line.series.measurement.as_str();
     if let Some(table_def) = db_schema.get_table(table_name) {
         if table_def.is_v3() {
             return Err(WriteLineError {
-                original_line: line.to_string(),
+                original_line: raw_line.to_string(),
                 line_number,
+                column: None,
                 error_message: "received v1 write protocol for a table that uses the v3 data model"
                     .to_string(),
             });
@@ -340,8 +378,9 @@ fn validate_v1_line<'a>(
                 if field_col_type != schema_col_type {
                     let field_name = field_name.to_string();
                     return Err(WriteLineError {
-                        original_line: line.to_string(),
-                        line_number: line_number + 1,
+                        original_line: raw_line.to_string(),
+                        line_number,
+                        column: None,
                         error_message: format!(
                         "invalid field value in line protocol for field '{field_name}' on line \
                         {line_number}: expected type {expected}, but got {got}",
@@ -708,4 +747,80 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn write_validator_error_line_numbers() -> Result<(), Error> {
+        let namespace = NamespaceName::new("test").unwrap();
+        let catalog = Arc::new(Catalog::new());
+        let result = WriteValidator::initialize(namespace, catalog)?
+            .v1_parse_lines_and_update_schema(
+                "cpu,tag1=foo val1=1 1\n\
+                \n\
+                # comment\n\
+                cpu,tag1=foo val1=\"bar\" 2\n\
+                cpu,tag1=foo val1=3 3\n\
+                cpu,tag1=foo val1= 4",
+                true,
+            )?
+            .convert_lines_to_buffer(
+                Time::from_timestamp_nanos(0),
+                SegmentDuration::new_5m(),
+                Precision::Auto,
+            );
+
+        assert_eq!(result.line_count, 2);
+        let errors = result
+            .errors
+            .iter()
+            .map(|e| (e.line_number, e.original_line.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                (4, "cpu,tag1=foo val1=\"bar\" 2"),
+                (6, "cpu,tag1=foo val1= 4")
+            ]
+        );
+
+        // Newlines in string field values and CRLF line endings are counted:
+        let result = WriteValidator::initialize(
+            NamespaceName::new("test").unwrap(),
+            Arc::new(Catalog::new()),
+        )?
+        .v1_parse_lines_and_update_schema(
+            "cpu,tag1=foo val1=\"multi\nline\" 1\r\n\
+            \r\n\
+            cpu,tag1=foo val2= 3\r\n\
+            cpu,tag1=foo val2=4 4\r\n",
+            true,
+        )?
+        .convert_lines_to_buffer(
+            Time::from_timestamp_nanos(0),
+            SegmentDuration::new_5m(),
+            Precision::Auto,
+        );
+
+        assert_eq!(result.line_count, 2);
+        let errors = result
+            .errors
+            .iter()
+            .map(|e| (e.line_number, e.original_line.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(errors, [(4, "cpu,tag1=foo val2= 3")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_error_column() {
+        let line = "cpu,tag1=foo val1=1,val2=\"open 1";
+        let error = influxdb_line_protocol::Error::CannotParseEntireLine {
+            trailing_content: ",val2=\"open 1".to_string(),
+        };
+        assert_eq!(super::parse_error_column(line, &error), Some(20));
+        assert_eq!(
+            super::parse_error_column(line, &influxdb_line_protocol::Error::FieldSetMissing),
+            None
+        );
+    }
 }